@@ -0,0 +1,87 @@
+use biocomputation::candidate::Candidate;
+use biocomputation::dataitem::DataItemInput;
+use biocomputation::dataset::DataSet;
+use biocomputation::ga_spec::{CalculatedSpecs, GaSpec, GaSpecInput};
+use biocomputation::population::Population;
+use biocomputation::rule::Constraint;
+use criterion::{criterion_group, criterion_main, Criterion};
+use rand::SeedableRng;
+use rand_pcg::Pcg64;
+
+/// The evaluation from before the inputs were packed, where the inputs were strings and every
+/// constraint looked its digit up with `chars().nth`
+fn calculate_fitness_by_string(candidate: &Candidate, items: &[(String, String)]) -> usize {
+    items
+        .iter()
+        .filter(|(input, output)| {
+            let prediction = candidate
+                .rules()
+                .iter()
+                .find(|rule| {
+                    rule.constraints()
+                        .iter()
+                        .all(|(index, constraint)| match constraint {
+                            Constraint::Character(character) => {
+                                *index < input.len()
+                                    && input.chars().nth(*index) == Some(*character)
+                            }
+                            Constraint::Interval { .. } => false,
+                        })
+                })
+                .map(|rule| rule.output())
+                .unwrap_or_else(|| candidate.default_output());
+            prediction == output
+        })
+        .count()
+}
+
+/// Compares checking the compiled rules against the packed inputs, checking every constraint
+/// against the packed inputs, and the string inputs they replaced, on a random population
+fn fitness(criterion: &mut Criterion) {
+    let spec_input: GaSpecInput = serde_json::from_str(include_str!("../input.json")).unwrap();
+    for name in &["data2", "data3"] {
+        let data_set = DataSet::from_file(format!("data/{}.txt", name))
+            .unwrap()
+            .binarize();
+        let calculated = CalculatedSpecs::from_training(&data_set, data_set.classes()).unwrap();
+        let spec: GaSpec = (spec_input.clone(), calculated).into();
+        let population = Population::generate(&mut Pcg64::seed_from_u64(0), &spec);
+        let string_items: Vec<(String, String)> = data_set
+            .as_ref()
+            .iter()
+            .map(|data_item| match data_item.input() {
+                DataItemInput::Binary(input) => (input.to_string(), data_item.output().to_owned()),
+                DataItemInput::Real(_) => unreachable!("the data set is binarized"),
+            })
+            .collect();
+
+        let mut group = criterion.benchmark_group(*name);
+        group.bench_function("compiled", |bencher| {
+            bencher.iter(|| {
+                for candidate in population.candidates() {
+                    candidate.calculate_fitness(&data_set).unwrap();
+                }
+            })
+        });
+        group.bench_function("by_constraint", |bencher| {
+            bencher.iter(|| {
+                for candidate in population.candidates() {
+                    candidate
+                        .calculate_fitness_by_constraint(&data_set)
+                        .unwrap();
+                }
+            })
+        });
+        group.bench_function("by_string", |bencher| {
+            bencher.iter(|| {
+                for candidate in population.candidates() {
+                    calculate_fitness_by_string(candidate, &string_items);
+                }
+            })
+        });
+        group.finish();
+    }
+}
+
+criterion_group!(benches, fitness);
+criterion_main!(benches);
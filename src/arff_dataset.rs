@@ -0,0 +1,303 @@
+use crate::csv_dataset::{resolve_target, split_record};
+use crate::dataitem::{parse_real, DataItem, DataItemInput, Row};
+use crate::dataset::{DataSet, DataSetParseError, TargetColumn};
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct ArffOptions {
+    /// The class attribute. Defaults to the last attribute
+    pub target: Option<TargetColumn>,
+}
+
+/// The type of an `@attribute`
+#[derive(Clone, PartialEq, Debug)]
+enum AttributeType {
+    Numeric,
+    /// Nominal values are read as their position within the declaration
+    Nominal(Vec<String>),
+}
+
+impl DataSet {
+    pub fn from_arff_file<T: AsRef<Path>>(
+        path: T,
+        options: &ArffOptions,
+    ) -> Result<DataSet, DataSetParseError> {
+        let file =
+            File::open(path.as_ref()).map_err(|err| DataSetParseError::IoError(err.kind()))?;
+        DataSet::from_arff_reader(BufReader::new(file), options)
+    }
+
+    /// Reads a data set in the Weka ARFF format. Numeric attributes are read as real valued
+    /// features and nominal attributes are read as the index of the value within the declaration.
+    /// Line numbers in errors start from 1
+    pub fn from_arff_reader<R: BufRead>(
+        reader: R,
+        options: &ArffOptions,
+    ) -> Result<DataSet, DataSetParseError> {
+        let mut data_set = DataSet::new();
+        for (line_number, row) in read_arff(reader, options, None)? {
+            let label = row
+                .label
+                .ok_or(DataSetParseError::MissingValue { line_number })?;
+            data_set
+                .push(DataItem::new(row.input, label))
+                .map_err(|source| DataSetParseError::DataSetError {
+                    line_number,
+                    source,
+                })?;
+        }
+
+        if data_set.is_empty() {
+            return Err(DataSetParseError::NoData);
+        }
+        Ok(data_set)
+    }
+}
+
+pub fn rows_from_arff_file<T: AsRef<Path>>(
+    path: T,
+    options: &ArffOptions,
+    width: usize,
+) -> Result<Vec<Row>, DataSetParseError> {
+    let file = File::open(path.as_ref()).map_err(|err| DataSetParseError::IoError(err.kind()))?;
+    rows_from_arff_reader(BufReader::new(file), options, width)
+}
+
+/// Reads inputs with `width` features for prediction. When exactly `width` attributes are
+/// declared there is no label, otherwise the class attribute is the label. A `?` label is read as
+/// missing
+pub fn rows_from_arff_reader<R: BufRead>(
+    reader: R,
+    options: &ArffOptions,
+    width: usize,
+) -> Result<Vec<Row>, DataSetParseError> {
+    Ok(read_arff(reader, options, Some(width))?
+        .into_iter()
+        .map(|(_, row)| row)
+        .collect())
+}
+
+/// Reads the records along with their line numbers. The records only lack a class attribute
+/// when `width` is given and that many attributes are declared
+fn read_arff<R: BufRead>(
+    reader: R,
+    options: &ArffOptions,
+    width: Option<usize>,
+) -> Result<Vec<(usize, Row)>, DataSetParseError> {
+    let mut rows = Vec::new();
+    let mut names: Vec<String> = Vec::new();
+    let mut attributes: Vec<AttributeType> = Vec::new();
+    let mut target_index: Option<Option<usize>> = None;
+
+    for (line_index, line) in reader.lines().enumerate() {
+        let line_number = line_index + 1;
+        let line = line.map_err(|err| DataSetParseError::IoError(err.kind()))?;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('%') {
+            continue;
+        }
+
+        let target = match target_index {
+            Some(target) => target,
+            None => {
+                let lowercase = line.to_ascii_lowercase();
+                if lowercase.starts_with("@attribute") {
+                    let (name, attribute_type) = parse_attribute(line, line_number)?;
+                    names.push(name);
+                    attributes.push(attribute_type);
+                } else if lowercase.starts_with("@data") {
+                    target_index = Some(if width == Some(names.len()) {
+                        None
+                    } else {
+                        Some(resolve_target(
+                            options.target.as_ref(),
+                            Some(&names),
+                            names.len(),
+                        )?)
+                    });
+                }
+                // @relation and anything else in the header is ignored
+                continue;
+            }
+        };
+
+        let label_columns = usize::from(target.is_some());
+        let fields = split_record(line, ',');
+        if fields.len() != attributes.len() {
+            return Err(DataSetParseError::WidthMismatch {
+                line_number,
+                expected: attributes.len().saturating_sub(label_columns),
+                found: fields.len().saturating_sub(label_columns),
+            });
+        }
+
+        let mut values = Vec::with_capacity(fields.len() - label_columns);
+        let mut label = None;
+        for (index, (field, attribute)) in fields.into_iter().zip(&attributes).enumerate() {
+            let is_target = Some(index) == target;
+            if field.is_empty() || field == "?" {
+                // Labels can be missing when reading inputs for prediction
+                if is_target && width.is_some() {
+                    continue;
+                }
+                return Err(DataSetParseError::MissingValue { line_number });
+            }
+            if is_target {
+                label = Some(field);
+                continue;
+            }
+            let value = match attribute {
+                AttributeType::Numeric => {
+                    parse_real(&field).map_err(|source| DataSetParseError::DataItemParseError {
+                        line_number,
+                        source,
+                    })?
+                }
+                AttributeType::Nominal(nominal_values) => nominal_values
+                    .iter()
+                    .position(|nominal_value| *nominal_value == field)
+                    .ok_or(DataSetParseError::UnknownNominalValue {
+                        line_number,
+                        value: field,
+                    })? as f64,
+            };
+            values.push(value);
+        }
+
+        rows.push((
+            line_number,
+            Row {
+                input: DataItemInput::Real(values),
+                label,
+            },
+        ));
+    }
+
+    if target_index.is_none() {
+        return Err(DataSetParseError::MissingDataSection);
+    }
+    Ok(rows)
+}
+
+/// Parses a `@attribute <name> <type>` line
+fn parse_attribute(
+    line: &str,
+    line_number: usize,
+) -> Result<(String, AttributeType), DataSetParseError> {
+    let rest = line["@attribute".len()..].trim_start();
+
+    // The name can be quoted to include spaces
+    let (name, rest) = match rest.chars().next() {
+        Some(quote) if quote == '\'' || quote == '"' => {
+            let end = rest[1..]
+                .find(quote)
+                .ok_or(DataSetParseError::InvalidAttribute { line_number })?;
+            (rest[1..=end].to_owned(), rest[end + 2..].trim())
+        }
+        Some(_) => {
+            let end = rest
+                .find(char::is_whitespace)
+                .ok_or(DataSetParseError::InvalidAttribute { line_number })?;
+            (rest[..end].to_owned(), rest[end..].trim())
+        }
+        None => return Err(DataSetParseError::InvalidAttribute { line_number }),
+    };
+
+    if rest.starts_with('{') && rest.ends_with('}') {
+        let values = split_record(&rest[1..rest.len() - 1], ',');
+        return Ok((name, AttributeType::Nominal(values)));
+    }
+
+    match rest.to_ascii_lowercase().as_str() {
+        "numeric" | "real" | "integer" => Ok((name, AttributeType::Numeric)),
+        "" => Err(DataSetParseError::InvalidAttribute { line_number }),
+        _ => Err(DataSetParseError::UnsupportedAttributeType {
+            line_number,
+            attribute_type: rest.to_owned(),
+        }),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const WEATHER: &str = "% comment
+@relation weather
+
+@attribute outlook {sunny, overcast, rainy}
+@attribute 'relative humidity' numeric
+@ATTRIBUTE play {yes, no}
+
+@data
+sunny,85,no
+overcast,86,yes
+";
+
+    #[test]
+    fn test_arff() {
+        let data_set = DataSet::from_arff_reader(WEATHER.as_bytes(), &ArffOptions::default())
+            .expect("data set is invalid");
+        assert_eq!(
+            data_set.as_ref(),
+            &vec![
+                DataItem::new(DataItemInput::Real(vec![0.0, 85.0]), "no".to_owned()),
+                DataItem::new(DataItemInput::Real(vec![1.0, 86.0]), "yes".to_owned()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_class_attribute() {
+        let options = ArffOptions {
+            target: Some(TargetColumn::Name("outlook".to_owned())),
+        };
+        let data_set =
+            DataSet::from_arff_reader(WEATHER.as_bytes(), &options).expect("data set is invalid");
+        assert_eq!(data_set.classes(), vec!["overcast", "sunny"]);
+    }
+
+    #[test]
+    fn test_rows() {
+        let rows = rows_from_arff_reader(
+            WEATHER.replace("no\n", "?\n").as_bytes(),
+            &ArffOptions::default(),
+            2,
+        )
+        .expect("rows are invalid");
+        assert_eq!(rows[0].label, None);
+        assert_eq!(rows[1].label, Some("yes".to_owned()));
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(
+            DataSet::from_arff_reader(
+                "@attribute a numeric\n@attribute b {x, y}\n@data\n1,z\n".as_bytes(),
+                &ArffOptions {
+                    target: Some(TargetColumn::Index(0))
+                }
+            ),
+            Err(DataSetParseError::UnknownNominalValue {
+                line_number: 4,
+                value: "z".to_owned()
+            })
+        );
+        assert_eq!(
+            DataSet::from_arff_reader(
+                "@attribute a date\n@attribute b {x, y}\n@data\n".as_bytes(),
+                &ArffOptions::default()
+            ),
+            Err(DataSetParseError::UnsupportedAttributeType {
+                line_number: 1,
+                attribute_type: "date".to_owned()
+            })
+        );
+        assert_eq!(
+            DataSet::from_arff_reader("@attribute a numeric\n".as_bytes(), &ArffOptions::default()),
+            Err(DataSetParseError::MissingDataSection)
+        );
+    }
+}
@@ -0,0 +1,154 @@
+use serde::{Deserialize, Serialize};
+use std::convert::TryFrom;
+use std::fmt::{self, Debug, Display};
+use std::str::FromStr;
+use thiserror::Error;
+
+const WORD_BITS: usize = 64;
+
+/// A fixed length list of bits packed into words. Written out as a string of binary digits
+#[derive(Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(into = "String", try_from = "String")]
+pub struct Bits {
+    words: Vec<u64>,
+    len: usize,
+}
+
+#[derive(Error, Debug, Clone, Copy, PartialEq)]
+#[error("'{0}' is not a binary digit")]
+pub struct BitsParseError(pub char);
+
+impl Bits {
+    /// All of the bits are unset
+    pub fn zeros(len: usize) -> Self {
+        Bits {
+            words: vec![0; len.div_ceil(WORD_BITS)],
+            len,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns none if the index is out of range
+    pub fn get(&self, index: usize) -> Option<bool> {
+        if index >= self.len {
+            return None;
+        }
+        Some(self.words[index / WORD_BITS] >> (index % WORD_BITS) & 1 == 1)
+    }
+
+    /// Does nothing if the index is out of range
+    pub fn set(&mut self, index: usize, value: bool) {
+        if index >= self.len {
+            return;
+        }
+        let mask = 1 << (index % WORD_BITS);
+        if value {
+            self.words[index / WORD_BITS] |= mask;
+        } else {
+            self.words[index / WORD_BITS] &= !mask;
+        }
+    }
+
+    /// Checks whether the bits equal `value` wherever `care` is set. Bits past the end of the
+    /// shorter of the two are ignored
+    pub fn matches(&self, care: &Bits, value: &Bits) -> bool {
+        self.words
+            .iter()
+            .zip(&care.words)
+            .zip(&value.words)
+            .all(|((word, care), value)| (word ^ value) & care == 0)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = bool> + '_ {
+        (0..self.len)
+            .map(move |index| self.words[index / WORD_BITS] >> (index % WORD_BITS) & 1 == 1)
+    }
+}
+
+impl std::iter::FromIterator<bool> for Bits {
+    fn from_iter<T: IntoIterator<Item = bool>>(iter: T) -> Self {
+        let mut bits = Bits::zeros(0);
+        for value in iter {
+            if bits.len.is_multiple_of(WORD_BITS) {
+                bits.words.push(0);
+            }
+            bits.len += 1;
+            bits.set(bits.len - 1, value);
+        }
+        bits
+    }
+}
+
+impl FromStr for Bits {
+    type Err = BitsParseError;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        input
+            .chars()
+            .map(|character| match character {
+                '0' => Ok(false),
+                '1' => Ok(true),
+                _ => Err(BitsParseError(character)),
+            })
+            .collect()
+    }
+}
+
+impl TryFrom<String> for Bits {
+    type Error = BitsParseError;
+
+    fn try_from(input: String) -> Result<Self, Self::Error> {
+        input.parse()
+    }
+}
+
+impl From<Bits> for String {
+    fn from(bits: Bits) -> Self {
+        bits.to_string()
+    }
+}
+
+impl Display for Bits {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for bit in self.iter() {
+            formatter.write_str(if bit { "1" } else { "0" })?;
+        }
+        Ok(())
+    }
+}
+
+impl Debug for Bits {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(formatter, "{:?}", self.to_string())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_matches() {
+        let input: Bits = "0110".repeat(20).parse().unwrap();
+        assert_eq!(input.len(), 80);
+        assert_eq!(input.to_string(), "0110".repeat(20));
+
+        let mut care = Bits::zeros(80);
+        let mut value = Bits::zeros(80);
+        care.set(1, true);
+        value.set(1, true);
+        care.set(79, true);
+        assert!(input.matches(&care, &value));
+
+        value.set(79, true);
+        assert!(!input.matches(&care, &value));
+        assert!("012".parse::<Bits>().is_err());
+    }
+}
@@ -1,22 +1,30 @@
+use crate::dataitem::DataItemInput;
 use crate::dataset::DataSet;
+use crate::fitness::Fitness;
 use crate::ga_spec::GaSpec;
-use crate::rule::{Rule, RuleEvaluationError};
-use rand::{self, Rng};
-use std::collections::HashSet;
+use crate::rule::{generate_output, BitRule, Rule, RuleEvaluationError};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
 use std::hash::{Hash, Hasher};
 use thiserror::Error;
 
-/// A candidate is a collection of rules
-#[derive(Debug, Clone, Eq)]
+/// A candidate is an ordered list of rules. The position of a rule within the list is its position
+/// within the genome.
+/// A candidate classifies an input as a decision list: the first rule that matches decides the
+/// output, and the default output is used when none of the rules match
+#[derive(Debug, Clone, Eq, Serialize, Deserialize)]
 pub struct Candidate {
-    rules: HashSet<Rule>,
+    rules: Vec<Rule>,
+    default_output: String,
+    #[serde(default)]
     mutation_count: usize,
+    #[serde(default)]
     birth_generation_id: Option<usize>,
 }
 
 impl PartialEq<Candidate> for Candidate {
     fn eq(&self, rhs: &Candidate) -> bool {
-        self.rules() == rhs.rules()
+        self.rules() == rhs.rules() && self.default_output() == rhs.default_output()
     }
 }
 
@@ -29,13 +37,14 @@ pub enum FitnessCalculationError {
 #[derive(Debug, PartialEq, Clone, Eq, Copy)]
 pub struct CandidateFitness<'a> {
     pub candidate: &'a Candidate,
-    pub fitness: usize,
+    pub fitness: Fitness,
 }
 
 impl Candidate {
-    pub fn from_rules(rules: &HashSet<Rule>) -> Self {
+    pub fn from_rules(rules: Vec<Rule>, default_output: String) -> Self {
         Self {
-            rules: rules.clone(),
+            rules,
+            default_output,
             mutation_count: 0,
             birth_generation_id: None,
         }
@@ -53,8 +62,21 @@ impl Candidate {
         self.mutation_count += 1;
     }
 
-    pub fn age(&mut self, current_generation_id: usize) -> Option<isize> {
-        self.birth_generation_id.clone().map(|birth_generation_id| {
+    /// Removes the repeated occurences of rules, keeping the first occurence of each
+    pub fn dedup_rules(&mut self) {
+        let mut seen: Vec<Rule> = Vec::with_capacity(self.rules.len());
+        self.rules.retain(|rule| {
+            if seen.contains(rule) {
+                false
+            } else {
+                seen.push(rule.clone());
+                true
+            }
+        });
+    }
+
+    pub fn age(&self, current_generation_id: usize) -> Option<isize> {
+        self.birth_generation_id.map(|birth_generation_id| {
             current_generation_id as isize - birth_generation_id as isize
         })
     }
@@ -67,56 +89,139 @@ impl Candidate {
         self.birth_generation_id
     }
 
-    pub fn rules(&self) -> &HashSet<Rule> {
+    pub fn rules(&self) -> &[Rule] {
         &self.rules
     }
 
-    pub fn rules_mut(&mut self) -> &mut HashSet<Rule> {
+    /// The number of constraints across all of the rules
+    pub fn constraint_count(&self) -> usize {
+        self.rules.iter().map(Rule::len).sum()
+    }
+
+    pub fn rules_mut(&mut self) -> &mut Vec<Rule> {
         &mut self.rules
     }
 
-    /// Fitness is simply the number of test data a candidate's ruleset can classify correctly
+    /// The output used when none of the rules match
+    pub fn default_output(&self) -> &str {
+        &self.default_output
+    }
+
+    pub fn set_default_output(&mut self, default_output: String) {
+        self.default_output = default_output;
+    }
+
+    /// Classifies an input. Returns the index of the rule that matched, if any, along with the
+    /// predicted output
+    pub fn classify(
+        &self,
+        input: &DataItemInput,
+    ) -> Result<(Option<usize>, &str), RuleEvaluationError> {
+        for (index, rule) in self.rules.iter().enumerate() {
+            if rule.evaluate(input)? {
+                return Ok((Some(index), rule.output()));
+            }
+        }
+        Ok((None, self.default_output()))
+    }
+
+    /// The output predicted for an input
+    pub fn predict(&self, input: &DataItemInput) -> Result<&str, RuleEvaluationError> {
+        self.classify(input).map(|(_, output)| output)
+    }
+
+    /// The rules compiled for binary inputs of `width` digits, each along with its output.
+    /// Returns none if any of the rules cannot be compiled
+    pub fn compile(&self, width: usize) -> Option<Vec<(BitRule, &str)>> {
+        self.rules
+            .iter()
+            .map(|rule| Some((rule.compile(width)?, rule.output())))
+            .collect()
+    }
+
+    /// The outputs predicted for each of the items of a data set. Binary data is checked against
+    /// the compiled rules, which gives the same result as checking the constraints one by one
+    pub fn predictions(&self, data_set: &DataSet) -> Result<Vec<&str>, FitnessCalculationError> {
+        let compiled = match data_set.width() {
+            Some(width) if !data_set.is_real() => self.compile(width),
+            _ => None,
+        };
+        let mut predictions = Vec::with_capacity(data_set.len());
+        for data_item in data_set.as_ref() {
+            let prediction = match (&compiled, data_item.input()) {
+                (Some(compiled), DataItemInput::Binary(input)) => compiled
+                    .iter()
+                    .find(|(rule, _)| rule.matches(input))
+                    .map(|(_, output)| *output)
+                    .unwrap_or_else(|| self.default_output()),
+                (_, input) => self.predict(input)?,
+            };
+            predictions.push(prediction);
+        }
+        Ok(predictions)
+    }
+
+    /// The number of items of a data set a candidate's ruleset can classify correctly. This is
+    /// the default fitness
     pub fn calculate_fitness(&self, data_set: &DataSet) -> Result<usize, FitnessCalculationError> {
+        Ok(self
+            .predictions(data_set)?
+            .into_iter()
+            .zip(data_set.as_ref())
+            .filter(|(prediction, data_item)| *prediction == data_item.output())
+            .count())
+    }
+
+    /// Calculates the fitness by evaluating every constraint of the rules against the inputs
+    pub fn calculate_fitness_by_constraint(
+        &self,
+        data_set: &DataSet,
+    ) -> Result<usize, FitnessCalculationError> {
         let mut fitness = 0;
 
         for data_item in data_set.as_ref() {
-            for rule in &self.rules {
-                let result = if rule.evaluate(data_item.as_str())? {
-                    "1"
-                } else {
-                    "0"
-                };
-
-                if result == data_item.output() {
-                    fitness += 1;
-                    break;
-                }
+            if self.predict(data_item.input())? == data_item.output() {
+                fitness += 1;
             }
         }
         Ok(fitness)
     }
 
+    /// The fraction of a data set a candidate's ruleset can classify correctly.
+    /// Returns none if the data set is empty
+    pub fn accuracy(&self, data_set: &DataSet) -> Result<Option<f64>, FitnessCalculationError> {
+        if data_set.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(
+            self.calculate_fitness(data_set)? as f64 / data_set.len() as f64,
+        ))
+    }
+
     pub fn generate<T: Rng>(mut rng: &mut T, spec: &GaSpec) -> Self {
         let number_of_rules: usize = rng.gen_range(
             spec.initial_generation.rules.min,
             spec.initial_generation.rules.max,
         );
-        let mut rules = HashSet::with_capacity(number_of_rules);
+        let mut rules = Vec::with_capacity(number_of_rules);
 
         let mut consecutive_fails = 0;
 
         while rules.len() < number_of_rules {
-            if !rules.insert(Rule::generate(&mut rng, spec)) {
+            let rule = Rule::generate(&mut rng, spec);
+            if spec.unique_rules && rules.contains(&rule) {
                 consecutive_fails += 1;
                 if consecutive_fails >= spec.initial_generation.rules.rng_fail_retries {
                     break;
                 }
             } else {
                 consecutive_fails = 0;
+                rules.push(rule);
             }
         }
         Candidate {
             rules,
+            default_output: generate_output(rng, spec),
             mutation_count: 0,
             birth_generation_id: None,
         }
@@ -125,8 +230,82 @@ impl Candidate {
 
 impl Hash for Candidate {
     fn hash<H: Hasher>(&self, state: &mut H) {
-        let mut rules: Vec<&Rule> = self.rules.iter().collect();
-        rules.sort_by_key(|rule| (rule.len(), rule.to_string()));
-        rules.hash(state);
+        self.rules.hash(state);
+        self.default_output.hash(state);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::population::Population;
+    use crate::rule::Constraint;
+    use rand::SeedableRng;
+    use rand_pcg::Pcg64;
+    use std::collections::BTreeMap;
+
+    fn data_set(lines: &[&str]) -> DataSet {
+        let mut data_set = DataSet::new();
+        for line in lines {
+            data_set.push(line.parse().unwrap()).unwrap();
+        }
+        data_set
+    }
+
+    /// A rule that checks the first digit, or matches everything without a digit
+    fn rule(first: Option<char>, output: &str) -> Rule {
+        let mut constraints = BTreeMap::new();
+        if let Some(first) = first {
+            constraints.insert(0, Constraint::Character(first));
+        }
+        Rule::new(constraints, output.to_owned())
+    }
+
+    #[test]
+    fn test_first_matching_rule_wins() {
+        let candidate =
+            Candidate::from_rules(vec![rule(Some('1'), "a"), rule(None, "b")], "c".to_owned());
+        let one = DataItemInput::Binary("10".parse().unwrap());
+        let zero = DataItemInput::Binary("01".parse().unwrap());
+        assert_eq!(candidate.classify(&one), Ok((Some(0), "a")));
+        assert_eq!(candidate.classify(&zero), Ok((Some(1), "b")));
+    }
+
+    #[test]
+    fn test_default_output() {
+        let candidate = Candidate::from_rules(vec![rule(Some('1'), "a")], "c".to_owned());
+        let zero = DataItemInput::Binary("01".parse().unwrap());
+        assert_eq!(candidate.classify(&zero), Ok((None, "c")));
+        assert_eq!(
+            candidate.calculate_fitness(&data_set(&["01 c", "10 a", "00 a"])),
+            Ok(2)
+        );
+    }
+
+    #[test]
+    fn test_contradicting_rules() {
+        // An always true rule predicting 1 ahead of an always false one predicting 0 used to
+        // count every item as correct
+        let candidate =
+            Candidate::from_rules(vec![rule(None, "1"), rule(Some('x'), "0")], "0".to_owned());
+        let data_set = data_set(&["00 0", "01 1", "10 0", "11 1"]);
+        assert_eq!(candidate.calculate_fitness(&data_set), Ok(2));
+        assert_eq!(candidate.calculate_fitness_by_constraint(&data_set), Ok(2));
+    }
+
+    #[test]
+    fn test_compiled_fitness() {
+        for path in &["data/data2.txt", "data/data3.txt"] {
+            let data_set = DataSet::from_file(path).unwrap().binarize();
+            let spec = GaSpec::for_tests(&data_set);
+            let population = Population::generate(&mut Pcg64::seed_from_u64(1), &spec);
+            for candidate in population.candidates() {
+                assert!(candidate.compile(data_set.width().unwrap()).is_some());
+                assert_eq!(
+                    candidate.calculate_fitness(&data_set),
+                    candidate.calculate_fitness_by_constraint(&data_set)
+                );
+            }
+        }
     }
 }
@@ -0,0 +1,141 @@
+use crate::ga::{DataSplits, GaState};
+use crate::ga_spec::GaSpecInput;
+use rand_pcg::Pcg64;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use thiserror::Error;
+
+/// The version of the checkpoint format written by this build
+pub const CHECKPOINT_VERSION: u32 = 2;
+
+/// Everything needed to carry on a run from the generation it was taken at
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Checkpoint {
+    pub version: u32,
+    /// The spec of the run, with the seed filled in
+    pub spec: GaSpecInput,
+    /// Sha256 of the spec file, kept for the exported model
+    pub spec_hash: String,
+    pub seed: u64,
+    pub classes: Vec<String>,
+    pub data_splits: DataSplits,
+    pub rng: Pcg64,
+    pub state: GaState,
+}
+
+#[derive(Error, Debug)]
+pub enum CheckpointError {
+    #[error("an io error occured")]
+    IoError(std::io::ErrorKind),
+
+    #[error("json error: {0}")]
+    JsonError(#[from] serde_json::Error),
+
+    #[error(
+        "checkpoint version {0} is not supported, expected version {}",
+        CHECKPOINT_VERSION
+    )]
+    UnsupportedVersion(u32),
+}
+
+impl Checkpoint {
+    pub fn from_file<T: AsRef<Path>>(path: T) -> Result<Checkpoint, CheckpointError> {
+        let contents = fs::read_to_string(path.as_ref())
+            .map_err(|err| CheckpointError::IoError(err.kind()))?;
+        Checkpoint::from_json(&contents)
+    }
+
+    pub fn from_json(contents: &str) -> Result<Checkpoint, CheckpointError> {
+        #[derive(Deserialize)]
+        struct Version {
+            version: u32,
+        }
+        let Version { version } = serde_json::from_str(contents)?;
+        if version != CHECKPOINT_VERSION {
+            return Err(CheckpointError::UnsupportedVersion(version));
+        }
+        Ok(serde_json::from_str(contents)?)
+    }
+
+    /// Writes the checkpoint next to the file first and then moves it in place, so that an
+    /// interrupted write never leaves a broken checkpoint behind
+    pub fn to_file<T: AsRef<Path>>(&self, path: T) -> Result<(), CheckpointError> {
+        let path = path.as_ref();
+        let mut temporary_path = path.as_os_str().to_owned();
+        temporary_path.push(".tmp");
+
+        let file =
+            File::create(&temporary_path).map_err(|err| CheckpointError::IoError(err.kind()))?;
+        let mut writer = BufWriter::new(file);
+        serde_json::to_writer(&mut writer, self)?;
+        writer
+            .flush()
+            .map_err(|err| CheckpointError::IoError(err.kind()))?;
+        fs::rename(&temporary_path, path).map_err(|err| CheckpointError::IoError(err.kind()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::dataset::DataSet;
+    use crate::ga::{self, GaError};
+    use crate::ga_spec::{CalculatedSpecs, GaSpec};
+    use rand::SeedableRng;
+
+    #[test]
+    fn test_resume() {
+        let mut spec = serde_json::to_value(GaSpecInput::for_tests()).unwrap();
+        spec["max_evolutions"] = 12.into();
+        spec["stop_at_optimum_fitness"] = false.into();
+        let spec: GaSpecInput = serde_json::from_value(spec).unwrap();
+
+        let data_set = DataSet::from_file("data/data1.txt").unwrap();
+        let classes = data_set.classes();
+        let data_splits = DataSplits {
+            training: data_set.clone(),
+            validation: None,
+            test: data_set,
+        };
+        let calculated =
+            CalculatedSpecs::from_training(&data_splits.training, classes.clone()).unwrap();
+        let ga_specs: GaSpec = (spec.clone(), calculated).into();
+
+        let mut rng = Pcg64::seed_from_u64(1);
+        let state = GaState::generate(&mut rng, &ga_specs);
+        let mut saved = None;
+        let uninterrupted = ga::resume(
+            &mut rng,
+            &ga_specs,
+            &data_splits,
+            state,
+            |state: &GaState, rng: &Pcg64| -> Result<(), GaError> {
+                if state.generations.len() == 5 {
+                    let checkpoint = Checkpoint {
+                        version: CHECKPOINT_VERSION,
+                        spec: spec.clone(),
+                        spec_hash: String::new(),
+                        seed: 1,
+                        classes: classes.clone(),
+                        data_splits: data_splits.clone(),
+                        rng: rng.clone(),
+                        state: state.clone(),
+                    };
+                    saved = Some(serde_json::to_string(&checkpoint).unwrap());
+                }
+                Ok(())
+            },
+        )
+        .unwrap();
+
+        let Checkpoint { mut rng, state, .. } = Checkpoint::from_json(&saved.unwrap()).unwrap();
+        let resumed = ga::resume(&mut rng, &ga_specs, &data_splits, state, |_, _| Ok(())).unwrap();
+        assert_eq!(resumed.generations, uninterrupted.generations);
+        assert_eq!(
+            resumed.champion.candidate.rules(),
+            uninterrupted.champion.candidate.rules()
+        );
+    }
+}
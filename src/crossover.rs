@@ -1,12 +1,12 @@
 use crate::candidate::Candidate;
 use crate::candidate::CandidateFitness;
+use crate::ga_spec::GaSpec;
 use crate::rule::Rule;
-use rand::{self, Rng};
-use serde::Deserialize;
-use std::collections::HashSet;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CrossoverStrategy {
     pub matchup_strategy: MatchupStrategy,
     #[serde(flatten)]
@@ -14,13 +14,13 @@ pub struct CrossoverStrategy {
     pub mating_strategy: MatingStrategy,
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CrossoverStrategyCommonOptions {
     pub mirroring: MirroringStrategy,
     pub crossover_rate: usize,
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "type")]
 #[serde(rename_all = "snake_case")]
 pub enum MatchupStrategy {
@@ -32,7 +32,7 @@ pub enum MatchupStrategy {
     LeastFittest,
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "setting")]
 #[serde(rename_all = "snake_case")]
 pub enum MirroringStrategy {
@@ -41,7 +41,7 @@ pub enum MirroringStrategy {
     Never,
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 #[serde(tag = "type")]
 pub enum MatingStrategy {
@@ -63,9 +63,10 @@ pub enum CrossoverError {
 impl CrossoverStrategy {
     /// Returns an iterator of matchups
     /// Assumes candidates is sorted
-    pub fn matchup<'a, 'b: 'a>(
+    pub fn matchup<'a, 'b: 'a, T: Rng>(
         &'b self,
-        candidates: &'b Vec<CandidateFitness<'a>>,
+        rng: &mut T,
+        candidates: &'b [CandidateFitness<'a>],
     ) -> Result<
         Box<dyn Iterator<Item = (CandidateFitness<'a>, CandidateFitness<'a>)> + 'b>,
         CrossoverError,
@@ -76,7 +77,7 @@ impl CrossoverStrategy {
                     .iter()
                     .take(candidates.len() - 1)
                     .zip(candidates.iter().skip(1).rev())
-                    .map(|(a, b)| (a.clone(), b.clone())),
+                    .map(|(a, b)| (*a, *b)),
             )),
 
             MatchupStrategy::NextFittest => Ok(Box::new(
@@ -84,7 +85,7 @@ impl CrossoverStrategy {
                     .iter()
                     .take(candidates.len() - 1)
                     .zip(candidates.iter().skip(1))
-                    .map(|(a, b)| (a.clone(), b.clone())),
+                    .map(|(a, b)| (*a, *b)),
             )),
 
             MatchupStrategy::Random {
@@ -92,10 +93,9 @@ impl CrossoverStrategy {
                 allow_duplicates,
             } => {
                 if candidates.len() == 1 && !allow_asexual {
-                    return Err(CrossoverError::CantGenerateNonAsexualMatchupWithOneCandidate);
+                    Err(CrossoverError::CantGenerateNonAsexualMatchupWithOneCandidate)
                 } else {
                     let mut matchups = Vec::with_capacity(candidates.len() - 1);
-                    let mut rng = rand::thread_rng();
                     'main: for candidate_index in 0..candidates.len() {
                         let mut matchup = rng.gen_range(0, candidates.len());
                         if !allow_asexual && matchup == candidate_index {
@@ -117,27 +117,29 @@ impl CrossoverStrategy {
                         }
                         matchups.push((candidate_index, matchup))
                     }
-                    Ok(Box::new(matchups.into_iter().map(move |(a, b)| {
-                        (candidates[a].clone(), candidates[b].clone())
-                    })))
+                    Ok(Box::new(
+                        matchups
+                            .into_iter()
+                            .map(move |(a, b)| (candidates[a], candidates[b])),
+                    ))
                 }
             }
         }
     }
 
-    pub fn crossover(
+    pub fn crossover<T: Rng>(
         &'_ self,
-        candidates: &Vec<CandidateFitness<'_>>,
+        rng: &mut T,
+        candidates: &[CandidateFitness<'_>],
+        ga_spec: &GaSpec,
     ) -> Result<Vec<Candidate>, CrossoverError> {
         let mut results = Vec::new();
 
-        let mut rng = rand::thread_rng();
-
         if rng.gen_ratio(self.options.crossover_rate as u32, 100) {
             return Ok(results);
         }
 
-        let matchup = self.matchup(candidates)?;
+        let matchup = self.matchup(rng, candidates)?;
         match &self.mating_strategy {
             MatingStrategy::SinglePointAtIndex { split_at } => {
                 for (a, b) in matchup {
@@ -166,21 +168,26 @@ impl CrossoverStrategy {
                         .iter()
                         .take(split_at_a)
                         .chain(b.candidate.rules().iter().skip(split_at_b))
-                        .map(|rule| rule.clone())
+                        .cloned()
                         .collect();
 
-                    results.push(Candidate::from_rules(&first_child));
+                    results.push(Candidate::from_rules(
+                        first_child,
+                        a.candidate.default_output().to_owned(),
+                    ));
                     let second_child = b
                         .candidate
                         .rules()
                         .iter()
                         .take(split_at_b)
                         .chain(a.candidate.rules().iter().skip(split_at_a))
-                        .map(|rule| rule.clone())
+                        .cloned()
                         .collect();
-                    results.push(Candidate::from_rules(&second_child));
+                    results.push(Candidate::from_rules(
+                        second_child,
+                        b.candidate.default_output().to_owned(),
+                    ));
                 }
-                Ok(results)
             }
             MatingStrategy::SinglePointAtPercentage { split_at } => {
                 for (a, b) in matchup {
@@ -210,26 +217,31 @@ impl CrossoverStrategy {
                         .iter()
                         .take(split_at_a)
                         .chain(b.candidate.rules().iter().skip(split_at_b))
-                        .map(|rule| rule.clone())
+                        .cloned()
                         .collect();
 
-                    results.push(Candidate::from_rules(&first_child));
+                    results.push(Candidate::from_rules(
+                        first_child,
+                        a.candidate.default_output().to_owned(),
+                    ));
                     let second_child = b
                         .candidate
                         .rules()
                         .iter()
                         .take(split_at_b)
                         .chain(a.candidate.rules().iter().skip(split_at_a))
-                        .map(|rule| rule.clone())
+                        .cloned()
                         .collect();
-                    results.push(Candidate::from_rules(&second_child));
+                    results.push(Candidate::from_rules(
+                        second_child,
+                        b.candidate.default_output().to_owned(),
+                    ));
                 }
-                Ok(results)
             }
             MatingStrategy::MultiPointAtIndices { split_at } => {
                 for (a, b) in matchup {
-                    let mut first_child: HashSet<Rule> = HashSet::new();
-                    let mut second_child: HashSet<Rule> = HashSet::new();
+                    let mut first_child: Vec<Rule> = Vec::new();
+                    let mut second_child: Vec<Rule> = Vec::new();
 
                     for (split_at_a_start, split_at_a_end) in split_at.iter() {
                         let split_at_a_start = *split_at_a_start as usize;
@@ -290,7 +302,7 @@ impl CrossoverStrategy {
                                         .skip(split_at_b_start)
                                         .take(split_at_b_end - split_at_b_start),
                                 )
-                                .map(|rule| rule.clone()),
+                                .cloned(),
                         );
 
                         second_child.extend(
@@ -306,36 +318,40 @@ impl CrossoverStrategy {
                                         .skip(split_at_a_start)
                                         .take(split_at_a_end - split_at_a_start),
                                 )
-                                .map(|rule| rule.clone()),
+                                .cloned(),
                         );
                     }
-                    results.push(Candidate::from_rules(&first_child));
-                    results.push(Candidate::from_rules(&second_child));
+                    results.push(Candidate::from_rules(
+                        first_child,
+                        a.candidate.default_output().to_owned(),
+                    ));
+                    results.push(Candidate::from_rules(
+                        second_child,
+                        b.candidate.default_output().to_owned(),
+                    ));
                 }
-                Ok(results)
             }
             MatingStrategy::MultiPointAtPercentages { split_at } => {
                 for (a, b) in matchup {
-                    let mut first_child: HashSet<Rule> = HashSet::new();
-                    let mut second_child: HashSet<Rule> = HashSet::new();
+                    let mut first_child: Vec<Rule> = Vec::new();
+                    let mut second_child: Vec<Rule> = Vec::new();
 
                     for (percent_split_at_a_start, percent_split_at_a_end) in split_at.iter() {
-                        let split_at_a_start = (((*percent_split_at_a_start as f64 / 100.0) as f64)
+                        let split_at_a_start = ((*percent_split_at_a_start as f64 / 100.0)
                             * a.candidate.rules().len() as f64)
                             as usize;
-                        let split_at_a_end = (((*percent_split_at_a_end as f64 / 100.0) as f64)
+                        let split_at_a_end = ((*percent_split_at_a_end as f64 / 100.0)
                             * a.candidate.rules().len() as f64)
                             as usize;
 
                         let split_at_a_start = std::cmp::min(split_at_a_start, split_at_a_end);
                         let split_at_a_end = std::cmp::max(split_at_a_start, split_at_a_end);
 
-                        let split_at_b_start_no_mirror =
-                            (((*percent_split_at_a_start as f64 / 100.0) as f64)
-                                * b.candidate.rules().len() as f64)
-                                as usize;
-                        let split_at_b_end_no_mirror = (((*percent_split_at_a_end as f64 / 100.0)
-                            as f64)
+                        let split_at_b_start_no_mirror = ((*percent_split_at_a_start as f64
+                            / 100.0)
+                            * b.candidate.rules().len() as f64)
+                            as usize;
+                        let split_at_b_end_no_mirror = ((*percent_split_at_a_end as f64 / 100.0)
                             * b.candidate.rules().len() as f64)
                             as usize;
 
@@ -385,7 +401,7 @@ impl CrossoverStrategy {
                                         .skip(split_at_b_start)
                                         .take(split_at_b_end - split_at_b_start),
                                 )
-                                .map(|rule| rule.clone()),
+                                .cloned(),
                         );
 
                         second_child.extend(
@@ -401,14 +417,119 @@ impl CrossoverStrategy {
                                         .skip(split_at_a_start)
                                         .take(split_at_a_end - split_at_a_start),
                                 )
-                                .map(|rule| rule.clone()),
+                                .cloned(),
                         );
                     }
-                    results.push(Candidate::from_rules(&first_child));
-                    results.push(Candidate::from_rules(&second_child));
+                    results.push(Candidate::from_rules(
+                        first_child,
+                        a.candidate.default_output().to_owned(),
+                    ));
+                    results.push(Candidate::from_rules(
+                        second_child,
+                        b.candidate.default_output().to_owned(),
+                    ));
                 }
-                Ok(results)
             }
         }
+
+        if ga_spec.unique_rules {
+            for child in results.iter_mut() {
+                child.dedup_rules();
+            }
+        }
+        Ok(results)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::dataset::DataSet;
+    use crate::fitness::Fitness;
+    use crate::rule::Constraint;
+    use rand::SeedableRng;
+    use rand_pcg::Pcg64;
+    use std::collections::BTreeMap;
+
+    fn spec() -> GaSpec {
+        let mut data_set = DataSet::new();
+        for line in &["00 a", "11 b"] {
+            data_set.push(line.parse().unwrap()).unwrap();
+        }
+        GaSpec::for_tests(&data_set)
+    }
+
+    /// A rule told apart from the others by its output
+    fn rule(output: &str) -> Rule {
+        let mut constraints = BTreeMap::new();
+        constraints.insert(0, Constraint::Character('1'));
+        Rule::new(constraints, output.to_owned())
+    }
+
+    fn genome(outputs: &[&str]) -> Candidate {
+        Candidate::from_rules(
+            outputs.iter().map(|output| rule(output)).collect(),
+            "a".to_owned(),
+        )
+    }
+
+    fn outputs(candidate: &Candidate) -> Vec<&str> {
+        candidate.rules().iter().map(Rule::output).collect()
+    }
+
+    /// Crosses the candidates over at the first rule, without mirroring
+    fn cross(candidates: &[Candidate], spec: &GaSpec) -> Vec<Candidate> {
+        let strategy = CrossoverStrategy {
+            matchup_strategy: MatchupStrategy::NextFittest,
+            options: CrossoverStrategyCommonOptions {
+                mirroring: MirroringStrategy::Never,
+                crossover_rate: 0,
+            },
+            mating_strategy: MatingStrategy::SinglePointAtIndex { split_at: 1 },
+        };
+        let candidates: Vec<CandidateFitness> = candidates
+            .iter()
+            .map(|candidate| CandidateFitness {
+                candidate,
+                fitness: Fitness::new(0.0),
+            })
+            .collect();
+        strategy
+            .crossover(&mut Pcg64::seed_from_u64(1), &candidates, spec)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_single_point_at_index() {
+        let spec = spec();
+        let parents = [
+            genome(&["a0", "a1", "a2", "a3"]),
+            genome(&["b0", "b1", "b2"]),
+        ];
+        let children = cross(&parents, &spec);
+        let children: Vec<Vec<&str>> = children.iter().map(outputs).collect();
+        assert_eq!(
+            children,
+            vec![vec!["a0", "b1", "b2"], vec!["b0", "a1", "a2", "a3"]]
+        );
+        assert_eq!(cross(&parents, &spec), cross(&parents, &spec));
+    }
+
+    #[test]
+    fn test_unique_rules() {
+        let mut spec = spec();
+        let parents = [genome(&["r1", "r2", "r3"]), genome(&["r3", "r1", "r4"])];
+        let children = cross(&parents, &spec);
+        let children: Vec<Vec<&str>> = children.iter().map(outputs).collect();
+        // The first occurence of each rule is kept where it was
+        assert_eq!(children, vec![vec!["r1", "r4"], vec!["r3", "r2"]]);
+
+        spec.unique_rules = false;
+        let children = cross(&parents, &spec);
+        let children: Vec<Vec<&str>> = children.iter().map(outputs).collect();
+        assert_eq!(
+            children,
+            vec![vec!["r1", "r1", "r4"], vec!["r3", "r2", "r3"]]
+        );
     }
 }
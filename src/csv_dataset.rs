@@ -0,0 +1,315 @@
+use crate::dataitem::{parse_real, DataItem, DataItemInput, Row};
+use crate::dataset::{DataSet, DataSetParseError, TargetColumn};
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct CsvOptions {
+    pub delimiter: char,
+    /// Whether or not the first line holds the names of the columns
+    pub has_header: bool,
+    /// The column holding the output. Defaults to the last column
+    pub target: Option<TargetColumn>,
+}
+
+impl Default for CsvOptions {
+    fn default() -> Self {
+        CsvOptions {
+            delimiter: ',',
+            has_header: true,
+            target: None,
+        }
+    }
+}
+
+impl DataSet {
+    pub fn from_csv_file<T: AsRef<Path>>(
+        path: T,
+        options: &CsvOptions,
+    ) -> Result<DataSet, DataSetParseError> {
+        let file =
+            File::open(path.as_ref()).map_err(|err| DataSetParseError::IoError(err.kind()))?;
+        DataSet::from_csv_reader(BufReader::new(file), options)
+    }
+
+    /// Reads a data set where each line is a record of delimiter separated values. All the columns
+    /// other than the target are read as real valued features. Line numbers in errors start from 1
+    pub fn from_csv_reader<R: BufRead>(
+        reader: R,
+        options: &CsvOptions,
+    ) -> Result<DataSet, DataSetParseError> {
+        let mut data_set = DataSet::new();
+        for (line_number, row) in read_csv(reader, options, None)? {
+            let label = row
+                .label
+                .ok_or(DataSetParseError::MissingValue { line_number })?;
+            data_set
+                .push(DataItem::new(row.input, label))
+                .map_err(|source| DataSetParseError::DataSetError {
+                    line_number,
+                    source,
+                })?;
+        }
+
+        if data_set.is_empty() {
+            return Err(DataSetParseError::NoData);
+        }
+        Ok(data_set)
+    }
+}
+
+pub fn rows_from_csv_file<T: AsRef<Path>>(
+    path: T,
+    options: &CsvOptions,
+    width: usize,
+) -> Result<Vec<Row>, DataSetParseError> {
+    let file = File::open(path.as_ref()).map_err(|err| DataSetParseError::IoError(err.kind()))?;
+    rows_from_csv_reader(BufReader::new(file), options, width)
+}
+
+/// Reads inputs with `width` features for prediction. Records that have exactly `width` columns
+/// have no label, otherwise the target column is the label. A `?` label is read as missing
+pub fn rows_from_csv_reader<R: BufRead>(
+    reader: R,
+    options: &CsvOptions,
+    width: usize,
+) -> Result<Vec<Row>, DataSetParseError> {
+    Ok(read_csv(reader, options, Some(width))?
+        .into_iter()
+        .map(|(_, row)| row)
+        .collect())
+}
+
+/// Reads the records along with their line numbers. The records only lack a target column when
+/// `width` is given and they have that many columns
+fn read_csv<R: BufRead>(
+    reader: R,
+    options: &CsvOptions,
+    width: Option<usize>,
+) -> Result<Vec<(usize, Row)>, DataSetParseError> {
+    let mut rows = Vec::new();
+    let mut names: Option<Vec<String>> = None;
+    let mut target_index: Option<Option<usize>> = None;
+
+    for (line_index, line) in reader.lines().enumerate() {
+        let line_number = line_index + 1;
+        let line = line.map_err(|err| DataSetParseError::IoError(err.kind()))?;
+        let line = line.trim_end();
+        if line.is_empty() {
+            continue;
+        }
+
+        let fields = split_record(line, options.delimiter);
+
+        if options.has_header && names.is_none() {
+            names = Some(fields);
+            continue;
+        }
+
+        let expected = names.as_ref().map(Vec::len).unwrap_or(fields.len());
+        let target = match target_index {
+            Some(target) => target,
+            None => {
+                let target = if width == Some(expected) {
+                    None
+                } else {
+                    Some(resolve_target(
+                        options.target.as_ref(),
+                        names.as_deref(),
+                        fields.len(),
+                    )?)
+                };
+                target_index = Some(target);
+                target
+            }
+        };
+
+        let label_columns = usize::from(target.is_some());
+        if fields.len() != expected || target.is_some_and(|target| target >= fields.len()) {
+            return Err(DataSetParseError::WidthMismatch {
+                line_number,
+                expected: expected.saturating_sub(label_columns),
+                found: fields.len().saturating_sub(label_columns),
+            });
+        }
+
+        let mut values = Vec::with_capacity(fields.len() - label_columns);
+        let mut label = None;
+        for (index, field) in fields.into_iter().enumerate() {
+            let field = field.trim();
+            let is_target = Some(index) == target;
+            if field.is_empty() || field == "?" {
+                // Labels can be missing when reading inputs for prediction
+                if is_target && width.is_some() {
+                    continue;
+                }
+                return Err(DataSetParseError::MissingValue { line_number });
+            }
+            if is_target {
+                label = Some(field.to_owned());
+                continue;
+            }
+            values.push(parse_real(field).map_err(|source| {
+                DataSetParseError::DataItemParseError {
+                    line_number,
+                    source,
+                }
+            })?);
+        }
+
+        rows.push((
+            line_number,
+            Row {
+                input: DataItemInput::Real(values),
+                label,
+            },
+        ));
+    }
+    Ok(rows)
+}
+
+/// Finds the index of the target column. Defaults to the last column
+pub(crate) fn resolve_target(
+    target: Option<&TargetColumn>,
+    names: Option<&[String]>,
+    width: usize,
+) -> Result<usize, DataSetParseError> {
+    match target {
+        None => width
+            .checked_sub(1)
+            .ok_or_else(|| DataSetParseError::UnknownColumn("last".to_owned())),
+        Some(TargetColumn::Index(index)) => {
+            if *index < width {
+                Ok(*index)
+            } else {
+                Err(DataSetParseError::UnknownColumn(index.to_string()))
+            }
+        }
+        Some(TargetColumn::Name(name)) => names
+            .and_then(|names| names.iter().position(|column| column == name))
+            .ok_or_else(|| DataSetParseError::UnknownColumn(name.clone())),
+    }
+}
+
+/// Splits a record on the delimiter. Fields can be wrapped in double or single quotes to include
+/// the delimiter, and a quote is escaped by doubling it
+pub(crate) fn split_record(line: &str, delimiter: char) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut quote: Option<char> = None;
+    let mut chars = line.chars().peekable();
+
+    while let Some(character) = chars.next() {
+        match quote {
+            Some(quote_character) if character == quote_character => {
+                if chars.peek() == Some(&quote_character) {
+                    field.push(quote_character);
+                    chars.next();
+                } else {
+                    quote = None;
+                }
+            }
+            Some(_) => field.push(character),
+            None if character == delimiter => {
+                fields.push(field.trim().to_owned());
+                field.clear();
+            }
+            None if (character == '"' || character == '\'') && field.trim().is_empty() => {
+                field.clear();
+                quote = Some(character);
+            }
+            None => field.push(character),
+        }
+    }
+    fields.push(field.trim().to_owned());
+    fields
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_split_record() {
+        assert_eq!(
+            split_record(r#"1, "a,b" ,'it''s',"#, ','),
+            vec!["1", "a,b", "it's", ""]
+        );
+        assert_eq!(split_record("1;2;3", ';'), vec!["1", "2", "3"]);
+    }
+
+    #[test]
+    fn test_target_by_name() {
+        let options = CsvOptions {
+            target: Some(TargetColumn::Name("class".to_owned())),
+            ..CsvOptions::default()
+        };
+        let data_set =
+            DataSet::from_csv_reader("class,x,y\nred,0.5,1\nblue,0.25,0\n".as_bytes(), &options)
+                .expect("data set is invalid");
+        assert_eq!(
+            data_set.as_ref()[1],
+            DataItem::new(DataItemInput::Real(vec![0.25, 0.0]), "blue".to_owned())
+        );
+    }
+
+    #[test]
+    fn test_without_header() {
+        let options = CsvOptions {
+            delimiter: ';',
+            has_header: false,
+            target: None,
+        };
+        let data_set = DataSet::from_csv_reader("0.5;1;a\n0.25;0;b\n".as_bytes(), &options)
+            .expect("data set is invalid");
+        assert_eq!(data_set.len(), 2);
+        assert_eq!(data_set.width(), Some(2));
+    }
+
+    #[test]
+    fn test_rows() {
+        let rows = rows_from_csv_reader("x,y\n0.5,1\n".as_bytes(), &CsvOptions::default(), 2)
+            .expect("rows are invalid");
+        assert_eq!(
+            rows,
+            vec![Row {
+                input: DataItemInput::Real(vec![0.5, 1.0]),
+                label: None
+            }]
+        );
+        let rows = rows_from_csv_reader(
+            "x,y,class\n0.5,1,a\n0.25,0,?\n".as_bytes(),
+            &CsvOptions::default(),
+            2,
+        )
+        .expect("rows are invalid");
+        assert_eq!(rows[0].label, Some("a".to_owned()));
+        assert_eq!(rows[1].label, None);
+    }
+
+    #[test]
+    fn test_errors() {
+        let options = CsvOptions::default();
+        assert_eq!(
+            DataSet::from_csv_reader("x,y,class\n0.5,1,a\n0.25,b\n".as_bytes(), &options),
+            Err(DataSetParseError::WidthMismatch {
+                line_number: 3,
+                expected: 2,
+                found: 1
+            })
+        );
+        assert_eq!(
+            DataSet::from_csv_reader("x,y,class\n0.5,,a\n".as_bytes(), &options),
+            Err(DataSetParseError::MissingValue { line_number: 2 })
+        );
+        let options = CsvOptions {
+            target: Some(TargetColumn::Name("label".to_owned())),
+            ..CsvOptions::default()
+        };
+        assert_eq!(
+            DataSet::from_csv_reader("x,y,class\n0.5,1,a\n".as_bytes(), &options),
+            Err(DataSetParseError::UnknownColumn("label".to_owned()))
+        );
+    }
+}
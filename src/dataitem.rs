@@ -1,40 +1,172 @@
+use crate::bits::Bits;
 use lazy_static::lazy_static;
 use regex::Regex;
+use serde::{Deserialize, Serialize};
 use std::str::FromStr;
 use thiserror::Error;
 
 lazy_static! {
     static ref DATA_ITEM_REGEX: Regex =
-        Regex::new(r##"^((?P<binary>[01]{5,6})|((?P<real_first>0\.\d{6})( (?P<real_second>0\.\d{6}))( (?P<real_third>0\.\d{6}))( (?P<real_fourth>0\.\d{6}))( (?P<real_fifth>0\.\d{6}))( (?P<real_sixth>0\.\d{6})))) (?P<output>[01])$"##)
-            .unwrap();
+        Regex::new(r##"^(?P<input>\S+(\s+\S+)*?)\s+(?P<output>\S+)$"##).unwrap();
+    static ref BINARY_REGEX: Regex = Regex::new(r##"^[01]+$"##).unwrap();
 }
 
-/// A `DataItem` is just an ascii string
-/// While working with this struct, it is assumed that all the characters in the string are:
-///     1. Valid uf8(Rust takes care of this since all the Strings in rust are valid ut8)
-///     2. Ascii digits or dot
-#[derive(Debug, PartialEq, Clone)]
+/// The input of a `DataItem`
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub enum DataItemInput {
+    /// Binary digits, packed so that a rule can be checked a word at a time
+    Binary(Bits),
+    /// A list of real valued features
+    Real(Vec<f64>),
+}
+
+/// A `DataItem` is an input paired with the expected output
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
 pub struct DataItem {
-    input: String,
+    input: DataItemInput,
     output: String,
 }
 
 impl DataItem {
+    pub fn new(input: DataItemInput, output: String) -> Self {
+        DataItem { input, output }
+    }
+
     pub fn output(&self) -> &str {
         &self.output
     }
 
-    pub fn as_str(&self) -> &str {
+    pub fn input(&self) -> &DataItemInput {
         &self.input
     }
 
-    /// Gets a character at an index. Returns none if it is out of range
+    pub fn is_real(&self) -> bool {
+        self.input.is_real()
+    }
+
+    /// Gets a character at an index. Returns none if it is out of range or if the input is real
+    /// valued
+    pub fn char_at(&self, index: usize) -> Option<char> {
+        self.input.char_at(index)
+    }
+
+    /// Gets the value at an index as a float. Binary digits are converted to 0.0 or 1.0
+    pub fn real_at(&self, index: usize) -> Option<f64> {
+        self.input.real_at(index)
+    }
+
+    pub fn width(&self) -> usize {
+        self.input.width()
+    }
+
+    /// Rounds every real valued feature to a binary digit
+    pub fn binarize(self) -> DataItem {
+        DataItem {
+            input: self.input.binarize(),
+            output: self.output,
+        }
+    }
+}
+
+impl DataItemInput {
+    pub fn is_real(&self) -> bool {
+        matches!(self, DataItemInput::Real(_))
+    }
+
+    /// Gets a character at an index. Returns none if it is out of range or if the input is real
+    /// valued
     pub fn char_at(&self, index: usize) -> Option<char> {
-        self.as_str().chars().nth(index)
+        match self {
+            DataItemInput::Binary(input) => input.get(index).map(|bit| if bit { '1' } else { '0' }),
+            DataItemInput::Real(_) => None,
+        }
+    }
+
+    /// Gets the value at an index as a float. Binary digits are converted to 0.0 or 1.0
+    pub fn real_at(&self, index: usize) -> Option<f64> {
+        match self {
+            DataItemInput::Binary(input) => input.get(index).map(|bit| if bit { 1.0 } else { 0.0 }),
+            DataItemInput::Real(input) => input.get(index).copied(),
+        }
     }
 
     pub fn width(&self) -> usize {
-        self.as_str().len()
+        match self {
+            DataItemInput::Binary(input) => input.len(),
+            DataItemInput::Real(input) => input.len(),
+        }
+    }
+
+    /// Rounds every real valued feature to a binary digit
+    pub fn binarize(self) -> DataItemInput {
+        match self {
+            DataItemInput::Binary(_) => self,
+            DataItemInput::Real(input) => DataItemInput::Binary(
+                input
+                    .into_iter()
+                    .map(|float_value| float_value.round())
+                    .map(|binary_float| binary_float != 0.0)
+                    .collect(),
+            ),
+        }
+    }
+
+    /// Parses whitespace separated features. A single run of binary digits is a binary input,
+    /// anything else is a list of real values
+    pub fn parse(features: &str) -> Result<DataItemInput, DataItemParseError> {
+        let binary = BINARY_REGEX.is_match(features.trim());
+        DataItemInput::parse_as(features, binary)
+    }
+
+    /// Parses whitespace separated features as a binary input or as a list of real values,
+    /// whichever the file they come from holds
+    pub fn parse_as(features: &str, binary: bool) -> Result<DataItemInput, DataItemParseError> {
+        if !features.is_ascii() {
+            return Err(DataItemParseError::NotValidAscii);
+        }
+        let features = features.trim();
+        if binary {
+            if !BINARY_REGEX.is_match(features) {
+                return Err(DataItemParseError::InvalidFormat);
+            }
+            // The regex only lets binary digits through
+            return Ok(DataItemInput::Binary(features.parse().unwrap()));
+        }
+        let values: Result<Vec<f64>, DataItemParseError> =
+            features.split_whitespace().map(parse_real).collect();
+        Ok(DataItemInput::Real(values?))
+    }
+}
+
+/// Whether a file of `variables` features holds binary inputs, judged by one of its lines with or
+/// without the output. A single binary digit reads the same as a real value of 0 or 1, so files
+/// of a single variable are taken as real valued
+pub fn is_binary_line(line: &str, variables: usize) -> bool {
+    let mut tokens = line.split_whitespace();
+    match tokens.next() {
+        Some(features) => {
+            variables > 1
+                && features.len() == variables
+                && BINARY_REGEX.is_match(features)
+                && tokens.count() <= 1
+        }
+        None => false,
+    }
+}
+
+/// An input read for prediction, along with its label when the data has one
+#[derive(Debug, PartialEq, Clone)]
+pub struct Row {
+    pub input: DataItemInput,
+    pub label: Option<String>,
+}
+
+impl From<DataItem> for Row {
+    fn from(data_item: DataItem) -> Self {
+        Row {
+            input: data_item.input,
+            label: Some(data_item.output),
+        }
     }
 }
 
@@ -48,6 +180,9 @@ pub enum DataItemParseError {
 
     #[error("float parse error")]
     FloatParseError(#[from] std::num::ParseFloatError),
+
+    #[error("real values have to be finite")]
+    NonFiniteValue,
 }
 
 // Allow an string types to be converted(falliably) to a DataItem
@@ -55,63 +190,49 @@ impl FromStr for DataItem {
     type Err = DataItemParseError;
 
     fn from_str(input: &str) -> Result<Self, Self::Err> {
-        if !input.is_ascii() {
-            return Err(DataItemParseError::NotValidAscii);
-        }
+        let (features, output) = split_output(input)?;
+        Ok(DataItem {
+            input: DataItemInput::parse(features)?,
+            output: output.to_owned(),
+        })
+    }
+}
 
-        match DATA_ITEM_REGEX.captures(input) {
-            Some(captures) => {
-                if let (Some(binary), Some(output)) =
-                    (captures.name("binary"), captures.name("output"))
-                {
-                    Ok(DataItem {
-                        input: binary.as_str().to_owned(),
-                        output: output.as_str().to_owned(),
-                    })
-                } else if let (
-                    Some(first),
-                    Some(second),
-                    Some(third),
-                    Some(fourth),
-                    Some(fifth),
-                    Some(sixth),
-                    Some(output),
-                ) = (
-                    captures.name("real_first"),
-                    captures.name("real_second"),
-                    captures.name("real_third"),
-                    captures.name("real_fourth"),
-                    captures.name("real_fifth"),
-                    captures.name("real_sixth"),
-                    captures.name("output"),
-                ) {
-                    let all = [first, second, third, fourth, fifth, sixth];
-                    let results: Result<Vec<f64>, std::num::ParseFloatError> = all
-                        .iter()
-                        .map(|capture| capture.as_str())
-                        .map(|float_as_str| float_as_str.parse::<f64>())
-                        .collect();
-                    let results = results?;
-                    let input: String = results
-                        .into_iter()
-                        .map(|float_value| float_value.round())
-                        .map(|binary_float| binary_float as usize)
-                        .map(|binary_usize| if binary_usize == 0 { "0" } else { "1" })
-                        .collect();
-
-                    Ok(DataItem {
-                        input,
-                        output: output.as_str().to_owned(),
-                    })
-                } else {
-                    unreachable!("shouldn't be reached")
-                }
-            }
-            None => Err(DataItemParseError::InvalidFormat),
-        }
+impl DataItem {
+    /// Parses a line of features followed by the output, with the features read as the
+    /// representation of the file the line comes from
+    pub fn parse_as(input: &str, binary: bool) -> Result<Self, DataItemParseError> {
+        let (features, output) = split_output(input)?;
+        Ok(DataItem {
+            input: DataItemInput::parse_as(features, binary)?,
+            output: output.to_owned(),
+        })
+    }
+}
+
+/// Splits a line into the features and the output that follows them
+fn split_output(input: &str) -> Result<(&str, &str), DataItemParseError> {
+    if !input.is_ascii() {
+        return Err(DataItemParseError::NotValidAscii);
+    }
+    let captures = DATA_ITEM_REGEX
+        .captures(input.trim())
+        .ok_or(DataItemParseError::InvalidFormat)?;
+    match (captures.name("input"), captures.name("output")) {
+        (Some(features), Some(output)) => Ok((features.as_str(), output.as_str())),
+        _ => Err(DataItemParseError::InvalidFormat),
     }
 }
 
+/// Parses a single real valued feature
+pub(crate) fn parse_real(value: &str) -> Result<f64, DataItemParseError> {
+    let value: f64 = value.parse()?;
+    if !value.is_finite() {
+        return Err(DataItemParseError::NonFiniteValue);
+    }
+    Ok(value)
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -120,12 +241,23 @@ mod test {
         assert_eq!(
             "00000 0".parse(),
             Ok(DataItem {
-                input: "00000".to_owned(),
+                input: DataItemInput::Binary("00000".parse().unwrap()),
                 output: "0".to_owned()
             })
         );
     }
 
+    #[test]
+    fn test_class_label() {
+        assert_eq!(
+            "00101 setosa".parse(),
+            Ok(DataItem {
+                input: DataItemInput::Binary("00101".parse().unwrap()),
+                output: "setosa".to_owned()
+            })
+        );
+    }
+
     #[test]
     fn test_non_digit() {
         assert_eq!(
@@ -135,16 +267,32 @@ mod test {
     }
 
     #[test]
-    fn test_one_ignored() {
+    fn test_real() {
         assert_eq!(
             "0.981136 0.369132 0.498354 0.067417 0.422276 0.803662 1".parse::<DataItem>(),
             Ok(DataItem {
-                input: "100001".to_owned(),
+                input: DataItemInput::Real(vec![
+                    0.981136, 0.369132, 0.498354, 0.067417, 0.422276, 0.803662
+                ]),
                 output: "1".to_owned()
             })
         );
     }
 
+    #[test]
+    fn test_binarize() {
+        let data_item =
+            DataItem::from_str("0.981136 0.369132 0.498354 0.067417 0.422276 0.803662 1")
+                .expect("data item input is invalid");
+        assert_eq!(
+            data_item.binarize(),
+            DataItem {
+                input: DataItemInput::Binary("100001".parse().unwrap()),
+                output: "1".to_owned()
+            }
+        );
+    }
+
     #[test]
     fn test_num_digits_width() {
         let data_item = DataItem::from_str("00001 1").expect("data item input is invalid");
@@ -156,6 +304,35 @@ mod test {
         assert_eq!(data_item.width(), 6);
     }
 
+    #[test]
+    fn test_any_width() {
+        assert_eq!(
+            "0110100 1"
+                .parse::<DataItem>()
+                .map(|data_item| data_item.width()),
+            Ok(7)
+        );
+        assert_eq!(
+            "0.5 -1.25 3 1e2 class_a".parse(),
+            Ok(DataItem {
+                input: DataItemInput::Real(vec![0.5, -1.25, 3.0, 100.0]),
+                output: "class_a".to_owned()
+            })
+        );
+    }
+
+    #[test]
+    fn test_invalid_real() {
+        assert!(matches!(
+            "0.5 abc 1".parse::<DataItem>(),
+            Err(DataItemParseError::FloatParseError(_))
+        ));
+        assert_eq!(
+            "0.5 inf 1".parse::<DataItem>(),
+            Err(DataItemParseError::NonFiniteValue)
+        );
+    }
+
     #[test]
     fn test_non_ascii() {
         assert_eq!(
@@ -169,7 +346,13 @@ mod test {
         let data_item =
             DataItem::from_str("0.981136 0.369132 0.498354 0.067417 0.422276 0.803662 1")
                 .expect("data item input is invalid");
-        assert_eq!(data_item.char_at(0), Some('9'));
-        assert_eq!(data_item.char_at(37), None);
+        assert_eq!(data_item.char_at(0), None);
+        assert_eq!(data_item.real_at(0), Some(0.981136));
+        assert_eq!(data_item.real_at(6), None);
+
+        let data_item = data_item.binarize();
+        assert_eq!(data_item.char_at(0), Some('1'));
+        assert_eq!(data_item.real_at(0), Some(1.0));
+        assert_eq!(data_item.char_at(6), None);
     }
 }
@@ -1,27 +1,128 @@
-use crate::dataitem::{DataItem, DataItemParseError};
+use crate::dataitem::{self, DataItem, DataItemInput, DataItemParseError, Row};
 use lazy_static::lazy_static;
+use rand::seq::SliceRandom;
+use rand::Rng;
 use regex::Regex;
+use serde::{Deserialize, Serialize};
 use std::fs::File;
 use std::io::{BufRead, BufReader};
 use std::path::Path;
 use thiserror::Error;
 
 lazy_static! {
-    static ref HEADER_REGEX: Regex = Regex::new(r##"^(\d+) rows x (\d+) variables"##).unwrap();
+    static ref HEADER_REGEX: Regex =
+        Regex::new(r##"^(?P<rows>\d+) rows x (?P<variables>\d+) variables"##).unwrap();
 }
 
 /// A dataset is a container contains a list of `DataItem`s
-#[derive(Clone, PartialEq, Debug)]
+#[derive(Clone, PartialEq, Debug, Default, Serialize, Deserialize)]
 pub struct DataSet(Vec<DataItem>);
 
+/// The formats a data set can be read from
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum DataFormat {
+    /// A `N rows x M variables` header followed by space separated data items
+    Text,
+    Csv,
+    Arff,
+}
+
+#[derive(Error, Clone, PartialEq, Debug)]
+#[error("unknown data format `{0}`, expected one of text, csv, arff")]
+pub struct DataFormatParseError(String);
+
+impl std::str::FromStr for DataFormat {
+    type Err = DataFormatParseError;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        match input.to_ascii_lowercase().as_str() {
+            "text" | "txt" => Ok(DataFormat::Text),
+            "csv" => Ok(DataFormat::Csv),
+            "arff" => Ok(DataFormat::Arff),
+            _ => Err(DataFormatParseError(input.to_owned())),
+        }
+    }
+}
+
+impl DataFormat {
+    /// Guesses the format from the extension of a file. Defaults to `Text`
+    pub fn from_path<T: AsRef<Path>>(path: T) -> DataFormat {
+        path.as_ref()
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .and_then(|extension| extension.parse().ok())
+            .unwrap_or(DataFormat::Text)
+    }
+}
+
+/// How the data items are divided when a data set is split
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum SplitStrategy {
+    /// Keeps the order of the file
+    Ordered,
+    /// Shuffles the data items before splitting
+    Shuffled,
+    /// Shuffles the data items and splits each class separately so that both halves have the same
+    /// ratio of classes
+    Stratified,
+}
+
+#[derive(Error, Clone, PartialEq, Debug)]
+#[error("unknown split strategy `{0}`, expected one of ordered, shuffled, stratified")]
+pub struct SplitStrategyParseError(String);
+
+impl std::str::FromStr for SplitStrategy {
+    type Err = SplitStrategyParseError;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        match input.to_ascii_lowercase().as_str() {
+            "ordered" => Ok(SplitStrategy::Ordered),
+            "shuffled" => Ok(SplitStrategy::Shuffled),
+            "stratified" => Ok(SplitStrategy::Stratified),
+            _ => Err(SplitStrategyParseError(input.to_owned())),
+        }
+    }
+}
+
+/// The column that holds the output of a data item
+#[derive(Clone, PartialEq, Debug)]
+pub enum TargetColumn {
+    /// Zero based index of the column
+    Index(usize),
+    /// Name of the column, as found in the header
+    Name(String),
+}
+
+impl std::str::FromStr for TargetColumn {
+    type Err = std::convert::Infallible;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        Ok(match input.parse() {
+            Ok(index) => TargetColumn::Index(index),
+            Err(_) => TargetColumn::Name(input.to_owned()),
+        })
+    }
+}
+
 impl DataSet {
+    pub fn new() -> Self {
+        DataSet(vec![])
+    }
+
     pub fn len(&self) -> usize {
         self.0.len()
     }
 
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
     pub fn push(&mut self, data_item: DataItem) -> Result<(), DataSetError> {
-        if self.0.len() > 0 {
-            if data_item.width() != self.0[0].width() {
+        if let Some(first) = self.0.first() {
+            if data_item.is_real() != first.is_real() {
+                return Err(DataSetError::HeterogenousData);
+            }
+            if data_item.width() != first.width() {
                 return Err(DataSetError::LengthMismatch);
             }
         }
@@ -33,6 +134,45 @@ impl DataSet {
         self.0.first().map(|v| v.width())
     }
 
+    pub fn is_real(&self) -> bool {
+        self.0.first().map(|v| v.is_real()).unwrap_or(false)
+    }
+
+    /// The distinct outputs found in the data set, sorted
+    pub fn classes(&self) -> Vec<String> {
+        let mut classes: Vec<String> = Vec::new();
+        for data_item in &self.0 {
+            if !classes.iter().any(|class| class == data_item.output()) {
+                classes.push(data_item.output().to_owned());
+            }
+        }
+        classes.sort();
+        classes
+    }
+
+    /// Rounds all the real valued items in the data set to binary digits
+    pub fn binarize(self) -> DataSet {
+        DataSet(self.0.into_iter().map(DataItem::binarize).collect())
+    }
+
+    /// The smallest and the largest value found in each of the features. Returns none if the
+    /// data set is empty
+    pub fn value_ranges(&self) -> Option<Vec<(f64, f64)>> {
+        let width = self.width()?;
+        let mut ranges: Vec<Option<(f64, f64)>> = vec![None; width];
+        for data_item in &self.0 {
+            for (index, range) in ranges.iter_mut().enumerate() {
+                if let Some(value) = data_item.real_at(index) {
+                    *range = Some(match *range {
+                        Some((min, max)) => (min.min(value), max.max(value)),
+                        None => (value, value),
+                    });
+                }
+            }
+        }
+        ranges.into_iter().collect()
+    }
+
     pub fn split_at_percentage(self, percentage: f64) -> Result<(DataSet, DataSet), DataSetError> {
         let split_index = (percentage / 100.0) * self.0.len() as f64;
         let split_index = split_index as usize;
@@ -49,6 +189,93 @@ impl DataSet {
         }
         Ok((DataSet(first_vec), DataSet(second_vec)))
     }
+
+    pub fn shuffle<T: Rng>(&mut self, rng: &mut T) {
+        self.0.shuffle(rng);
+    }
+
+    /// Splits the data set so that the first half has `percentage` percent of the data items
+    pub fn split<T: Rng>(
+        mut self,
+        rng: &mut T,
+        percentage: f64,
+        strategy: SplitStrategy,
+    ) -> Result<(DataSet, DataSet), DataSetError> {
+        if !(0.0..=100.0).contains(&percentage) {
+            return Err(DataSetError::InvalidPercentage);
+        }
+        match strategy {
+            SplitStrategy::Ordered => self.split_at_percentage(percentage),
+            SplitStrategy::Shuffled => {
+                self.shuffle(rng);
+                self.split_at_percentage(percentage)
+            }
+            SplitStrategy::Stratified => {
+                let mut first = DataSet::new();
+                let mut second = DataSet::new();
+                for class in self.classes() {
+                    let (class_first, class_second) = self.filter_by_output(&class).split(
+                        rng,
+                        percentage,
+                        SplitStrategy::Shuffled,
+                    )?;
+                    first.0.extend(class_first.0);
+                    second.0.extend(class_second.0);
+                }
+                first.shuffle(rng);
+                second.shuffle(rng);
+                Ok((first, second))
+            }
+        }
+    }
+
+    /// Partitions the data set into `count` folds. Each class is shuffled and dealt out to the
+    /// folds in turn, so that every fold has close to the same ratio of classes
+    pub fn stratified_folds<T: Rng>(
+        &self,
+        rng: &mut T,
+        count: usize,
+    ) -> Result<Vec<DataSet>, DataSetError> {
+        if count < 2 || count > self.len() {
+            return Err(DataSetError::InvalidFoldCount(count));
+        }
+        let mut folds = vec![DataSet::new(); count];
+        // Keep dealing from where the previous class stopped so the fold sizes stay balanced
+        let mut next_fold = 0;
+        for class in self.classes() {
+            let mut class_items = self.filter_by_output(&class);
+            class_items.shuffle(rng);
+            for data_item in class_items.0 {
+                folds[next_fold].0.push(data_item);
+                next_fold = (next_fold + 1) % count;
+            }
+        }
+        for fold in folds.iter_mut() {
+            fold.shuffle(rng);
+        }
+        Ok(folds)
+    }
+
+    /// Joins data sets together, keeping their order
+    pub fn concat(data_sets: &[&DataSet]) -> DataSet {
+        DataSet(
+            data_sets
+                .iter()
+                .flat_map(|data_set| data_set.0.iter().cloned())
+                .collect(),
+        )
+    }
+
+    /// The data items that have the given output
+    pub fn filter_by_output(&self, output: &str) -> DataSet {
+        DataSet(
+            self.0
+                .iter()
+                .filter(|data_item| data_item.output() == output)
+                .cloned()
+                .collect(),
+        )
+    }
 }
 
 impl std::convert::AsRef<Vec<DataItem>> for DataSet {
@@ -67,6 +294,9 @@ pub enum DataSetError {
 
     #[error("percentage should be between 0 and 100")]
     InvalidPercentage,
+
+    #[error("cannot make {0} folds, expected at least 2 and at most one per data item")]
+    InvalidFoldCount(usize),
 }
 
 #[derive(Error, Clone, PartialEq, Debug)]
@@ -77,14 +307,48 @@ pub enum DataSetParseError {
     #[error("file has no data")]
     NoData,
 
-    #[error("failed to parse data item due to {source}")]
+    #[error("the first line is not a valid `N rows x M variables` header")]
+    InvalidHeader,
+
+    #[error("header declares {expected} rows but the file has {found}")]
+    RowCountMismatch { expected: usize, found: usize },
+
+    #[error("expected {expected} variables but line {line_number} has {found}")]
+    WidthMismatch {
+        line_number: usize,
+        expected: usize,
+        found: usize,
+    },
+
+    #[error("column `{0}` does not exist")]
+    UnknownColumn(String),
+
+    #[error("line {line_number} is missing a value")]
+    MissingValue { line_number: usize },
+
+    #[error("line {line_number} is not a valid attribute declaration")]
+    InvalidAttribute { line_number: usize },
+
+    #[error("attribute type `{attribute_type}` on line {line_number} is not supported")]
+    UnsupportedAttributeType {
+        line_number: usize,
+        attribute_type: String,
+    },
+
+    #[error("`{value}` on line {line_number} is not one of the declared nominal values")]
+    UnknownNominalValue { line_number: usize, value: String },
+
+    #[error("file has no @data section")]
+    MissingDataSection,
+
+    #[error("failed to parse data item on line {line_number} due to {source}")]
     DataItemParseError {
         line_number: usize,
         #[source]
         source: DataItemParseError,
     },
 
-    #[error("failed to add item to data set due to: {source}")]
+    #[error("failed to add item on line {line_number} to data set due to: {source}")]
     DataSetError {
         line_number: usize,
         #[source]
@@ -96,22 +360,50 @@ impl DataSet {
     pub fn from_file<T: AsRef<Path>>(path: T) -> Result<DataSet, DataSetParseError> {
         let path = path.as_ref();
         let file = File::open(path).map_err(|err| DataSetParseError::IoError(err.kind()))?;
-        let reader = BufReader::new(file);
+        DataSet::from_reader(BufReader::new(file))
+    }
 
+    /// Reads a data set that starts with a `N rows x M variables` header, followed by a data
+    /// item on each line. Line numbers in errors start from 1
+    pub fn from_reader<R: BufRead>(reader: R) -> Result<DataSet, DataSetParseError> {
         let mut data_set = DataSet(vec![]);
+        let mut header: Option<(usize, usize)> = None;
+        let mut binary: Option<bool> = None;
 
-        for (line_number, line) in reader.lines().enumerate() {
+        for (line_index, line) in reader.lines().enumerate() {
+            let line_number = line_index + 1;
             let line = line.map_err(|err| DataSetParseError::IoError(err.kind()))?;
-            if line_number == 0 {
-                if !HEADER_REGEX.is_match(&line) { /**/ }
+            // The data files use CR line endings
+            let line = line.trim_end();
+
+            let (_, variables) = match header {
+                Some(header) => header,
+                None => {
+                    header = Some(parse_header(line)?);
+                    continue;
+                }
+            };
+
+            if line.is_empty() {
                 continue;
             }
-            let data_item: DataItem =
-                line.parse()
-                    .map_err(|err| DataSetParseError::DataItemParseError {
-                        source: err,
-                        line_number,
-                    })?;
+            // The representation is decided by the first row, so that a real value that happens
+            // to look like binary digits is not read as such
+            let binary = *binary.get_or_insert_with(|| dataitem::is_binary_line(line, variables));
+
+            let data_item = DataItem::parse_as(line, binary).map_err(|err| {
+                DataSetParseError::DataItemParseError {
+                    source: err,
+                    line_number,
+                }
+            })?;
+            if data_item.width() != variables {
+                return Err(DataSetParseError::WidthMismatch {
+                    line_number,
+                    expected: variables,
+                    found: data_item.width(),
+                });
+            }
             data_set
                 .push(data_item)
                 .map_err(|source| DataSetParseError::DataSetError {
@@ -119,9 +411,240 @@ impl DataSet {
                     source,
                 })?;
         }
-        if data_set.len() == 0 {
+
+        let (rows, _) = header.ok_or(DataSetParseError::InvalidHeader)?;
+        if data_set.is_empty() {
             return Err(DataSetParseError::NoData);
         }
+        if data_set.len() != rows {
+            return Err(DataSetParseError::RowCountMismatch {
+                expected: rows,
+                found: data_set.len(),
+            });
+        }
         Ok(data_set)
     }
 }
+
+/// Parses a `N rows x M variables` header into the number of rows and variables
+fn parse_header(line: &str) -> Result<(usize, usize), DataSetParseError> {
+    let captures = HEADER_REGEX
+        .captures(line)
+        .ok_or(DataSetParseError::InvalidHeader)?;
+    let rows = captures["rows"]
+        .parse()
+        .map_err(|_| DataSetParseError::InvalidHeader)?;
+    let variables = captures["variables"]
+        .parse()
+        .map_err(|_| DataSetParseError::InvalidHeader)?;
+    Ok((rows, variables))
+}
+
+pub fn rows_from_file<T: AsRef<Path>>(path: T) -> Result<Vec<Row>, DataSetParseError> {
+    let file = File::open(path.as_ref()).map_err(|err| DataSetParseError::IoError(err.kind()))?;
+    rows_from_reader(BufReader::new(file))
+}
+
+/// Reads inputs for prediction from the same format as `DataSet::from_reader`, except that each
+/// line may leave out the output
+pub fn rows_from_reader<R: BufRead>(reader: R) -> Result<Vec<Row>, DataSetParseError> {
+    let mut rows = Vec::new();
+    let mut header: Option<(usize, usize)> = None;
+    let mut binary: Option<bool> = None;
+
+    for (line_index, line) in reader.lines().enumerate() {
+        let line_number = line_index + 1;
+        let line = line.map_err(|err| DataSetParseError::IoError(err.kind()))?;
+        let line = line.trim_end();
+
+        let (_, variables) = match header {
+            Some(header) => header,
+            None => {
+                header = Some(parse_header(line)?);
+                continue;
+            }
+        };
+
+        if line.is_empty() {
+            continue;
+        }
+        let binary = *binary.get_or_insert_with(|| dataitem::is_binary_line(line, variables));
+
+        // A labeled line is only taken as such if the input has the declared width, otherwise the
+        // last real value would be mistaken for a label
+        let row = match DataItem::parse_as(line, binary) {
+            Ok(data_item) if data_item.width() == variables => Row::from(data_item),
+            _ => Row {
+                input: DataItemInput::parse_as(line, binary).map_err(|source| {
+                    DataSetParseError::DataItemParseError {
+                        line_number,
+                        source,
+                    }
+                })?,
+                label: None,
+            },
+        };
+        if row.input.width() != variables {
+            return Err(DataSetParseError::WidthMismatch {
+                line_number,
+                expected: variables,
+                found: row.input.width(),
+            });
+        }
+        rows.push(row);
+    }
+
+    let (expected_rows, _) = header.ok_or(DataSetParseError::InvalidHeader)?;
+    if rows.is_empty() {
+        return Err(DataSetParseError::NoData);
+    }
+    if rows.len() != expected_rows {
+        return Err(DataSetParseError::RowCountMismatch {
+            expected: expected_rows,
+            found: rows.len(),
+        });
+    }
+    Ok(rows)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rand::SeedableRng;
+    use rand_pcg::Pcg64;
+
+    #[test]
+    fn test_stratified_split() {
+        let mut data_set = DataSet::new();
+        for index in 0..40 {
+            let output = if index % 4 == 0 { "1" } else { "0" };
+            data_set
+                .push(format!("{:06b} {}", index, output).parse().unwrap())
+                .unwrap();
+        }
+        let mut rng = Pcg64::seed_from_u64(0);
+        let (first, second) = data_set
+            .split(&mut rng, 50.0, SplitStrategy::Stratified)
+            .unwrap();
+        assert_eq!(first.len(), 20);
+        assert_eq!(second.len(), 20);
+        assert_eq!(first.filter_by_output("1").len(), 5);
+        assert_eq!(second.filter_by_output("1").len(), 5);
+    }
+
+    #[test]
+    fn test_stratified_folds() {
+        let mut data_set = DataSet::new();
+        for index in 0..32 {
+            let output = if index % 4 == 0 { "1" } else { "0" };
+            data_set
+                .push(format!("{:05b} {}", index, output).parse().unwrap())
+                .unwrap();
+        }
+        let mut rng = Pcg64::seed_from_u64(0);
+        let folds = data_set.stratified_folds(&mut rng, 4).unwrap();
+        assert_eq!(folds.len(), 4);
+        for fold in &folds {
+            assert_eq!(fold.len(), 8);
+            assert_eq!(fold.filter_by_output("1").len(), 2);
+        }
+        let fold_references: Vec<&DataSet> = folds.iter().collect();
+        assert_eq!(DataSet::concat(&fold_references).len(), 32);
+        assert_eq!(
+            data_set.stratified_folds(&mut rng, 1),
+            Err(DataSetError::InvalidFoldCount(1))
+        );
+        assert_eq!(
+            data_set.stratified_folds(&mut rng, 33),
+            Err(DataSetError::InvalidFoldCount(33))
+        );
+    }
+
+    #[test]
+    fn test_header() {
+        let data_set =
+            DataSet::from_reader("2 rows x 3 variables\r\n010 1\r\n110 0\r\n".as_bytes())
+                .expect("data set is invalid");
+        assert_eq!(data_set.len(), 2);
+        assert_eq!(data_set.width(), Some(3));
+    }
+
+    #[test]
+    fn test_value_ranges() {
+        let data_set =
+            DataSet::from_reader("3 rows x 2 variables\n0 60.5 a\n2 96 b\n1 71 a\n".as_bytes())
+                .expect("data set is invalid");
+        assert_eq!(
+            data_set.value_ranges(),
+            Some(vec![(0.0, 2.0), (60.5, 96.0)])
+        );
+        assert_eq!(DataSet::new().value_ranges(), None);
+    }
+
+    #[test]
+    fn test_single_real_feature() {
+        let data_set = DataSet::from_reader("3 rows x 1 variables\n0.5 a\n1 b\n10 a\n".as_bytes())
+            .expect("data set is invalid");
+        assert!(data_set.is_real());
+        assert_eq!(
+            data_set.as_ref()[1].input(),
+            &DataItemInput::Real(vec![1.0])
+        );
+        assert_eq!(
+            data_set.as_ref()[2].input(),
+            &DataItemInput::Real(vec![10.0])
+        );
+
+        // The first row decides, even when it looks like binary digits
+        let data_set = DataSet::from_reader("2 rows x 1 variables\n1 a\n0.5 b\n".as_bytes())
+            .expect("data set is invalid");
+        assert!(data_set.is_real());
+    }
+
+    #[test]
+    fn test_rows() {
+        let rows = rows_from_reader("2 rows x 2 variables\n01 1\n10\n".as_bytes())
+            .expect("rows are invalid");
+        assert_eq!(rows[0].label, Some("1".to_owned()));
+        assert_eq!(rows[1].input, DataItemInput::Binary("10".parse().unwrap()));
+        assert_eq!(rows[1].label, None);
+
+        let rows = rows_from_reader("2 rows x 2 variables\n0.5 0.25\n1 0 a\n".as_bytes())
+            .expect("rows are invalid");
+        assert_eq!(rows[0].input, DataItemInput::Real(vec![0.5, 0.25]));
+        assert_eq!(rows[0].label, None);
+        assert_eq!(rows[1].input, DataItemInput::Real(vec![1.0, 0.0]));
+        assert_eq!(rows[1].label, Some("a".to_owned()));
+    }
+
+    #[test]
+    fn test_invalid_header() {
+        assert_eq!(
+            DataSet::from_reader("010 1\n110 0\n".as_bytes()),
+            Err(DataSetParseError::InvalidHeader)
+        );
+    }
+
+    #[test]
+    fn test_row_count_mismatch() {
+        assert_eq!(
+            DataSet::from_reader("3 rows x 3 variables\n010 1\n110 0\n".as_bytes()),
+            Err(DataSetParseError::RowCountMismatch {
+                expected: 3,
+                found: 2
+            })
+        );
+    }
+
+    #[test]
+    fn test_width_mismatch() {
+        assert_eq!(
+            DataSet::from_reader("2 rows x 2 variables\n0.1 0.2 1\n0.3 0.4 0.5 0\n".as_bytes()),
+            Err(DataSetParseError::WidthMismatch {
+                line_number: 3,
+                expected: 2,
+                found: 3
+            })
+        );
+    }
+}
@@ -0,0 +1,135 @@
+use crate::candidate::{Candidate, CandidateFitness};
+use serde::{Deserialize, Serialize};
+
+/// Protects the fittest candidates of a generation from mutation and replacement
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type")]
+#[serde(rename_all = "snake_case")]
+pub enum ElitismStrategy {
+    /// A fixed number of candidates
+    Count { count: usize },
+    /// A percentage of the population, rounded down
+    Percentage { percentage: f64 },
+}
+
+impl ElitismStrategy {
+    /// The number of elites for a population of the given size
+    pub fn size(&self, population_size: usize) -> usize {
+        let size = match self {
+            ElitismStrategy::Count { count } => *count,
+            ElitismStrategy::Percentage { percentage } => {
+                ((percentage / 100.0) * population_size as f64) as usize
+            }
+        };
+        std::cmp::min(size, population_size)
+    }
+
+    /// Picks the elites out of the candidates
+    /// Assumes candidates is sorted
+    pub fn select<'a>(&self, candidates: &[CandidateFitness<'a>]) -> Vec<CandidateFitness<'a>> {
+        let size = self.size(candidates.len());
+        candidates.iter().rev().take(size).copied().collect()
+    }
+}
+
+/// Takes ownership of the elites so that they can be used while the population is being changed
+pub fn to_owned_candidates(elites: &[CandidateFitness<'_>]) -> Vec<Candidate> {
+    elites.iter().map(|elite| elite.candidate.clone()).collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::dataset::DataSet;
+    use crate::fitness::Fitness;
+    use crate::fitness_cache::FitnessCache;
+    use crate::ga_spec::GaSpec;
+    use crate::population::Population;
+    use crate::replacement::{
+        ReplacementStrategy, ReplacementStrategyCommonOptions, ReplacementStrategyVariant,
+    };
+    use rand::SeedableRng;
+    use rand_pcg::Pcg64;
+
+    #[test]
+    fn test_size() {
+        assert_eq!(ElitismStrategy::Count { count: 2 }.size(5), 2);
+        assert_eq!(ElitismStrategy::Count { count: 10 }.size(5), 5);
+        assert_eq!(ElitismStrategy::Percentage { percentage: 50.0 }.size(5), 2);
+        assert_eq!(ElitismStrategy::Percentage { percentage: 33.4 }.size(10), 3);
+        assert_eq!(ElitismStrategy::Percentage { percentage: 50.0 }.size(0), 0);
+    }
+
+    #[test]
+    fn test_select() {
+        let candidates: Vec<Candidate> = ["0", "1", "2", "3"]
+            .iter()
+            .map(|output| Candidate::from_rules(vec![], (*output).to_owned()))
+            .collect();
+        // Sorted from the least to the most fit
+        let fitness: Vec<CandidateFitness> = candidates
+            .iter()
+            .enumerate()
+            .map(|(index, candidate)| CandidateFitness {
+                candidate,
+                fitness: Fitness::from(index),
+            })
+            .collect();
+
+        let elites = ElitismStrategy::Count { count: 2 }.select(&fitness);
+        assert_eq!(
+            to_owned_candidates(&elites),
+            vec![candidates[3].clone(), candidates[2].clone()]
+        );
+        let elites = ElitismStrategy::Percentage { percentage: 50.0 }.select(&fitness[..3]);
+        assert_eq!(to_owned_candidates(&elites), vec![candidates[2].clone()]);
+        assert!(ElitismStrategy::Count { count: 2 }.select(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_elites_survive() {
+        let data_set = DataSet::from_file("data/data2.txt").unwrap();
+        let mut spec = GaSpec::for_tests(&data_set);
+        let options = &mut spec.mutation.options;
+        options.chance = Some(100);
+        options.chance_per_candidate = Some(100);
+        options.chance_per_constraint = Some(100);
+
+        let mut rng = Pcg64::seed_from_u64(1);
+        let mut population = Population::generate(&mut rng, &spec);
+        let mut cache = FitnessCache::new(0);
+        let fitness = population
+            .calculate_fitness(&data_set, &spec, &mut cache)
+            .unwrap();
+        let elites = to_owned_candidates(&ElitismStrategy::Count { count: 1 }.select(&fitness));
+
+        let mutated = spec
+            .mutation
+            .mutate(&mut rng, &mut population, &elites, &spec)
+            .unwrap();
+        assert_eq!(mutated, population.len() - 1);
+        assert!(population.contains(&elites[0]));
+
+        // Generational replacement puts the offsprings first, so only the elite keeps its place
+        let offsprings = Population::generate(&mut rng, &spec)
+            .candidates()
+            .iter()
+            .cloned()
+            .collect();
+        ReplacementStrategy {
+            options: ReplacementStrategyCommonOptions { population_size: 1 },
+            variant: ReplacementStrategyVariant::Generational,
+        }
+        .replace(
+            &mut population,
+            offsprings,
+            &elites,
+            &data_set,
+            &spec,
+            &mut cache,
+        )
+        .unwrap();
+        assert_eq!(population.len(), 1);
+        assert!(population.contains(&elites[0]));
+    }
+}
@@ -0,0 +1,483 @@
+use crate::candidate::{Candidate, FitnessCalculationError};
+use crate::dataset::DataSet;
+use crate::metrics::{self, ClassMetrics};
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use std::fmt::{self, Debug, Display};
+use std::hash::{Hash, Hasher};
+
+/// The fitness of a candidate. The greater the fitter.
+/// A float that is totally ordered, so that candidates can be sorted by it. NaN is a fitness of
+/// zero, and so is negative zero. Candidates with the same value are told apart by the tie break,
+/// which is only set by the `tie_break` parsimony. The score of the fitness function before any
+/// parsimony pressure is carried along to tell whether the candidate is optimal, and takes no part
+/// in comparisons
+#[derive(Clone, Copy, Default, Serialize, Deserialize)]
+pub struct Fitness {
+    value: f64,
+    #[serde(default)]
+    tie_break: f64,
+    score: f64,
+}
+
+/// Turns NaN and negative zero into zero
+fn normalise(value: f64) -> f64 {
+    if value.is_nan() {
+        0.0
+    } else {
+        // Adding zero turns negative zero into zero
+        value + 0.0
+    }
+}
+
+impl Fitness {
+    /// A fitness without parsimony pressure, so the score is the value
+    pub fn new(value: f64) -> Self {
+        Fitness::penalised(value, value)
+    }
+
+    /// A fitness whose value is the score after parsimony pressure
+    pub fn penalised(value: f64, score: f64) -> Self {
+        Fitness {
+            value: normalise(value),
+            tie_break: 0.0,
+            score: normalise(score),
+        }
+    }
+
+    /// Out of two fitnesses with the same value, the one with the greater tie break is fitter
+    pub fn with_tie_break(self, tie_break: f64) -> Self {
+        Fitness {
+            tie_break: normalise(tie_break),
+            ..self
+        }
+    }
+
+    pub fn value(self) -> f64 {
+        self.value
+    }
+
+    /// The score of the fitness function before any parsimony pressure
+    pub fn score(self) -> f64 {
+        self.score
+    }
+}
+
+impl From<f64> for Fitness {
+    fn from(value: f64) -> Self {
+        Fitness::new(value)
+    }
+}
+
+impl From<usize> for Fitness {
+    fn from(value: usize) -> Self {
+        Fitness::new(value as f64)
+    }
+}
+
+impl From<Fitness> for f64 {
+    fn from(fitness: Fitness) -> Self {
+        fitness.value
+    }
+}
+
+impl PartialEq for Fitness {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Fitness {}
+
+impl PartialOrd for Fitness {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Fitness {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.value
+            .total_cmp(&other.value)
+            .then_with(|| self.tie_break.total_cmp(&other.tie_break))
+    }
+}
+
+impl Hash for Fitness {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.value.to_bits().hash(state);
+        self.tie_break.to_bits().hash(state);
+    }
+}
+
+impl Display for Fitness {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Display::fmt(&self.value, formatter)
+    }
+}
+
+impl Debug for Fitness {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Display::fmt(&self.value, formatter)
+    }
+}
+
+/// A metric in the range of 0 to 1 that can be combined into a weighted sum
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FitnessMetric {
+    Accuracy,
+    /// The mean recall of the classes that occur in the data set
+    BalancedAccuracy,
+    /// The F1 score averaged over the classes that occur in the data set
+    F1,
+    /// The Matthews correlation coefficient, moved from the range of -1 to 1 into the range of
+    /// 0 to 1
+    Mcc,
+}
+
+/// A metric along with how much it counts towards a weighted sum
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct WeightedMetric {
+    pub metric: FitnessMetric,
+    pub weight: f64,
+}
+
+/// How the fitness of a candidate is calculated, along with the pressure towards smaller
+/// candidates
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct FitnessSpec {
+    #[serde(flatten)]
+    pub function: FitnessFunction,
+    #[serde(default)]
+    pub parsimony: Option<ParsimonySpec>,
+}
+
+/// Favours candidates with fewer rules and constraints, which are easier to read
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type")]
+#[serde(rename_all = "snake_case")]
+pub enum ParsimonySpec {
+    /// Subtracts a penalty for every rule and every constraint. The penalties are in the units of
+    /// the fitness function: items for `correct` and a ratio for the rest. A negative fitness is
+    /// a fitness of zero
+    Linear {
+        #[serde(default)]
+        per_rule: f64,
+        #[serde(default)]
+        per_constraint: f64,
+    },
+    /// Out of two candidates with the same fitness, the one with fewer rules and constraints is
+    /// fitter
+    TieBreak,
+    /// Candidates with more rules or constraints than the caps have a fitness of zero
+    SizeCap {
+        #[serde(default)]
+        max_rules: Option<usize>,
+        #[serde(default)]
+        max_constraints: Option<usize>,
+    },
+}
+
+/// How the fitness of a candidate is calculated from its predictions on the training data
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(tag = "type")]
+#[serde(rename_all = "snake_case")]
+pub enum FitnessFunction {
+    /// The number of items classified correctly
+    #[default]
+    Correct,
+    Accuracy,
+    BalancedAccuracy,
+    F1,
+    Mcc,
+    /// The weighted mean of the recall of the classes that occur in the data set. Classes that
+    /// are missing from the weights have a weight of 1
+    WeightedClassAccuracy {
+        weights: BTreeMap<String, f64>,
+    },
+    /// The sum of the weighted metrics. A negative sum is a fitness of zero
+    WeightedSum {
+        terms: Vec<WeightedMetric>,
+    },
+}
+
+impl FitnessMetric {
+    fn ratio(&self, class_metrics: &[ClassMetrics], size: usize) -> f64 {
+        let correct: usize = class_metrics
+            .iter()
+            .map(|class_metrics| class_metrics.true_positives)
+            .sum();
+        match self {
+            FitnessMetric::Accuracy => {
+                if size == 0 {
+                    0.0
+                } else {
+                    correct as f64 / size as f64
+                }
+            }
+            FitnessMetric::BalancedAccuracy => {
+                metrics::macro_average(class_metrics, ClassMetrics::recall).unwrap_or_default()
+            }
+            FitnessMetric::F1 => {
+                metrics::macro_average(class_metrics, ClassMetrics::f1).unwrap_or_default()
+            }
+            FitnessMetric::Mcc => {
+                (metrics::matthews_correlation(class_metrics, correct, size).unwrap_or_default()
+                    + 1.0)
+                    / 2.0
+            }
+        }
+    }
+}
+
+impl FitnessSpec {
+    pub fn calculate(
+        &self,
+        candidate: &Candidate,
+        data_set: &DataSet,
+    ) -> Result<Fitness, FitnessCalculationError> {
+        let rules = candidate.rules().len();
+        let constraints = candidate.constraint_count();
+        let unpenalised = self.function.score(candidate, data_set)?;
+        let mut score = unpenalised;
+        match self.parsimony {
+            Some(ParsimonySpec::Linear {
+                per_rule,
+                per_constraint,
+            }) => {
+                score -= per_rule * rules as f64 + per_constraint * constraints as f64;
+            }
+            Some(ParsimonySpec::SizeCap {
+                max_rules,
+                max_constraints,
+            }) => {
+                let too_large = max_rules.is_some_and(|max_rules| rules > max_rules)
+                    || max_constraints.is_some_and(|max_constraints| constraints > max_constraints);
+                if too_large {
+                    score = 0.0;
+                }
+            }
+            Some(ParsimonySpec::TieBreak) | None => {}
+        }
+        let fitness = Fitness::penalised(score.max(0.0), unpenalised);
+
+        if let Some(ParsimonySpec::TieBreak) = self.parsimony {
+            // The smaller the candidate the greater the tie break
+            return Ok(fitness.with_tie_break(-((rules + constraints) as f64)));
+        }
+        Ok(fitness)
+    }
+}
+
+impl FitnessFunction {
+    /// The score of a candidate that classifies all of the data set correctly
+    pub fn optimum(&self, data_set: &DataSet) -> f64 {
+        let class_metrics = tally(
+            data_set
+                .as_ref()
+                .iter()
+                .map(|data_item| (data_item.output(), data_item.output())),
+        );
+        self.ratio(&class_metrics, data_set.len())
+    }
+
+    /// The fitness before any parsimony pressure
+    fn score(
+        &self,
+        candidate: &Candidate,
+        data_set: &DataSet,
+    ) -> Result<f64, FitnessCalculationError> {
+        if let FitnessFunction::Correct = self {
+            return Ok(candidate.calculate_fitness(data_set)? as f64);
+        }
+        let predictions = candidate.predictions(data_set)?;
+        let class_metrics = tally(
+            data_set
+                .as_ref()
+                .iter()
+                .map(|data_item| data_item.output())
+                .zip(predictions),
+        );
+        Ok(self.ratio(&class_metrics, data_set.len()))
+    }
+
+    fn ratio(&self, class_metrics: &[ClassMetrics], size: usize) -> f64 {
+        match self {
+            FitnessFunction::Correct => class_metrics
+                .iter()
+                .map(|class_metrics| class_metrics.true_positives)
+                .sum::<usize>() as f64,
+            FitnessFunction::Accuracy => FitnessMetric::Accuracy.ratio(class_metrics, size),
+            FitnessFunction::BalancedAccuracy => {
+                FitnessMetric::BalancedAccuracy.ratio(class_metrics, size)
+            }
+            FitnessFunction::F1 => FitnessMetric::F1.ratio(class_metrics, size),
+            FitnessFunction::Mcc => FitnessMetric::Mcc.ratio(class_metrics, size),
+            FitnessFunction::WeightedClassAccuracy { weights } => {
+                let mut weighted_sum = 0.0;
+                let mut total_weight = 0.0;
+                for class_metrics in class_metrics {
+                    if let Some(recall) = class_metrics.recall() {
+                        let weight = weights.get(&class_metrics.class).copied().unwrap_or(1.0);
+                        weighted_sum += weight * recall;
+                        total_weight += weight;
+                    }
+                }
+                if total_weight == 0.0 {
+                    0.0
+                } else {
+                    weighted_sum / total_weight
+                }
+            }
+            FitnessFunction::WeightedSum { terms } => terms
+                .iter()
+                .map(|term| term.weight * term.metric.ratio(class_metrics, size))
+                .sum(),
+        }
+    }
+}
+
+/// Counts the actual and predicted classes of each of the items
+fn tally<'a, T: Iterator<Item = (&'a str, &'a str)>>(outcomes: T) -> Vec<ClassMetrics> {
+    fn index_of(class: &str, class_metrics: &mut Vec<ClassMetrics>) -> usize {
+        match class_metrics
+            .iter()
+            .position(|existing| existing.class == class)
+        {
+            Some(index) => index,
+            None => {
+                class_metrics.push(ClassMetrics {
+                    class: class.to_owned(),
+                    support: 0,
+                    predicted: 0,
+                    true_positives: 0,
+                });
+                class_metrics.len() - 1
+            }
+        }
+    }
+
+    let mut class_metrics: Vec<ClassMetrics> = Vec::new();
+    for (actual, predicted) in outcomes {
+        let actual_index = index_of(actual, &mut class_metrics);
+        class_metrics[actual_index].support += 1;
+        let predicted_index = index_of(predicted, &mut class_metrics);
+        class_metrics[predicted_index].predicted += 1;
+        if actual_index == predicted_index {
+            class_metrics[actual_index].true_positives += 1;
+        }
+    }
+    class_metrics
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::rule::{Constraint, Rule};
+
+    #[test]
+    fn test_fitness() {
+        let mut constraints = BTreeMap::new();
+        constraints.insert(0, Constraint::Character('1'));
+        let candidate =
+            Candidate::from_rules(vec![Rule::new(constraints, "1".to_owned())], "0".to_owned());
+        let mut data_set = DataSet::new();
+        for line in &["1 1", "1 0", "0 0", "0 0"] {
+            data_set.push(line.parse().unwrap()).unwrap();
+        }
+        let evaluation = candidate.evaluate(&data_set, &[]).unwrap();
+        let close = |fitness: Fitness, ratio: f64| (fitness.value() - ratio).abs() < 1e-12;
+
+        let fitness = |function: FitnessFunction| {
+            FitnessSpec {
+                function,
+                parsimony: None,
+            }
+            .calculate(&candidate, &data_set)
+            .unwrap()
+        };
+        assert_eq!(fitness(FitnessFunction::Correct), Fitness::from(3));
+        assert!(close(fitness(FitnessFunction::Accuracy), 0.75));
+        assert!(close(
+            fitness(FitnessFunction::BalancedAccuracy),
+            evaluation.balanced_accuracy.unwrap()
+        ));
+        assert!(close(
+            fitness(FitnessFunction::F1),
+            evaluation.macro_f1.unwrap()
+        ));
+        assert!(close(
+            fitness(FitnessFunction::Mcc),
+            (evaluation.mcc.unwrap() + 1.0) / 2.0
+        ));
+
+        let mut weights = BTreeMap::new();
+        weights.insert("1".to_owned(), 3.0);
+        assert!(close(
+            fitness(FitnessFunction::WeightedClassAccuracy { weights }),
+            (2.0 / 3.0 + 3.0) / 4.0
+        ));
+        assert!(close(
+            fitness(FitnessFunction::WeightedSum {
+                terms: vec![
+                    WeightedMetric {
+                        metric: FitnessMetric::Accuracy,
+                        weight: 0.5,
+                    },
+                    WeightedMetric {
+                        metric: FitnessMetric::F1,
+                        weight: 0.5,
+                    },
+                ],
+            }),
+            0.5 * 0.75 + 0.5 * evaluation.macro_f1.unwrap()
+        ));
+
+        assert_eq!(FitnessFunction::Correct.optimum(&data_set), 4.0);
+        assert_eq!(FitnessFunction::Mcc.optimum(&data_set), 1.0);
+    }
+
+    #[test]
+    fn test_parsimony() {
+        let mut constraints = BTreeMap::new();
+        constraints.insert(0, Constraint::Character('1'));
+        constraints.insert(1, Constraint::Character('1'));
+        let rule = Rule::new(constraints, "1".to_owned());
+        let small = Candidate::from_rules(vec![rule.clone()], "0".to_owned());
+        // The second rule never fires, so both candidates classify the same way
+        let large = Candidate::from_rules(vec![rule.clone(), rule], "0".to_owned());
+        let mut data_set = DataSet::new();
+        for line in &["11 1", "10 0", "00 0", "01 0"] {
+            data_set.push(line.parse().unwrap()).unwrap();
+        }
+
+        let fitness = |parsimony: ParsimonySpec, candidate: &Candidate| {
+            FitnessSpec {
+                function: FitnessFunction::Correct,
+                parsimony: Some(parsimony),
+            }
+            .calculate(candidate, &data_set)
+            .unwrap()
+        };
+        let linear = ParsimonySpec::Linear {
+            per_rule: 0.5,
+            per_constraint: 0.25,
+        };
+        assert_eq!(fitness(linear, &small), Fitness::from(3));
+        assert_eq!(fitness(linear, &large), Fitness::from(2));
+        assert!(
+            fitness(ParsimonySpec::TieBreak, &small) > fitness(ParsimonySpec::TieBreak, &large)
+        );
+        // The size only breaks ties, so the value is the score
+        assert_eq!(fitness(ParsimonySpec::TieBreak, &large).value(), 4.0);
+        let size_cap = ParsimonySpec::SizeCap {
+            max_rules: Some(1),
+            max_constraints: None,
+        };
+        assert_eq!(fitness(size_cap, &small), Fitness::from(4));
+        assert_eq!(fitness(size_cap, &large), Fitness::from(0));
+        assert_eq!(fitness(size_cap, &large).score(), 4.0);
+    }
+}
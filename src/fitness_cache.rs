@@ -0,0 +1,112 @@
+use crate::candidate::Candidate;
+use crate::fitness::Fitness;
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::DefaultHasher;
+use std::hash::BuildHasherDefault;
+
+/// Remembers the fitness of the candidates that were already evaluated, so that the candidates
+/// that survive a generation are not evaluated again. Candidates are told apart by their rules and
+/// default output, so a cache is only valid for the data set it was filled on.
+/// Holds at most `capacity` candidates, dropping the least recently used ones first
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct FitnessCache {
+    capacity: usize,
+    #[serde(with = "indexmap::map::serde_seq")]
+    entries: IndexMap<Candidate, Fitness, BuildHasherDefault<DefaultHasher>>,
+    hits: usize,
+    misses: usize,
+}
+
+impl FitnessCache {
+    /// A capacity of zero turns the cache off
+    pub fn new(capacity: usize) -> Self {
+        FitnessCache {
+            capacity,
+            entries: IndexMap::default(),
+            hits: 0,
+            misses: 0,
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// The number of lookups that found a fitness so far
+    pub fn hits(&self) -> usize {
+        self.hits
+    }
+
+    /// The number of lookups that did not find a fitness so far
+    pub fn misses(&self) -> usize {
+        self.misses
+    }
+
+    /// Looks up the fitness of a candidate, marking it as the most recently used
+    pub fn get(&mut self, candidate: &Candidate) -> Option<Fitness> {
+        match self.entries.get_full(candidate) {
+            Some((index, _, fitness)) => {
+                let fitness = *fitness;
+                let last = self.entries.len() - 1;
+                self.entries.move_index(index, last);
+                self.hits += 1;
+                Some(fitness)
+            }
+            None => {
+                self.misses += 1;
+                None
+            }
+        }
+    }
+
+    pub fn insert(&mut self, candidate: Candidate, fitness: Fitness) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.entries.len() >= self.capacity && !self.entries.contains_key(&candidate) {
+            self.entries.shift_remove_index(0);
+        }
+        self.entries.insert(candidate, fitness);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::rule::Rule;
+    use std::collections::BTreeMap;
+
+    fn candidate(output: &str) -> Candidate {
+        Candidate::from_rules(
+            vec![Rule::new(BTreeMap::new(), output.to_owned())],
+            "0".to_owned(),
+        )
+    }
+
+    #[test]
+    fn test_least_recently_used() {
+        let mut cache = FitnessCache::new(2);
+        cache.insert(candidate("a"), Fitness::from(1));
+        cache.insert(candidate("b"), Fitness::from(2));
+        assert_eq!(cache.get(&candidate("a")), Some(Fitness::from(1)));
+        cache.insert(candidate("c"), Fitness::from(3));
+
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.get(&candidate("b")), None);
+        assert_eq!(cache.get(&candidate("c")), Some(Fitness::from(3)));
+        assert_eq!((cache.hits(), cache.misses()), (2, 1));
+
+        let json = serde_json::to_string(&cache).unwrap();
+        let mut cache: FitnessCache = serde_json::from_str(&json).unwrap();
+        assert_eq!(cache.get(&candidate("a")), Some(Fitness::from(1)));
+    }
+}
@@ -0,0 +1,472 @@
+use crate::candidate::{Candidate, CandidateFitness, FitnessCalculationError};
+use crate::checkpoint::CheckpointError;
+use crate::crossover::CrossoverError;
+use crate::dataset::DataSet;
+use crate::elitism;
+use crate::fitness::Fitness;
+use crate::fitness_cache::FitnessCache;
+use crate::ga_spec::GaSpec;
+use crate::metrics;
+use crate::mutation::MutationError;
+use crate::pareto::{self, ParetoMember};
+use crate::population::Population;
+use crate::replacement::ReplacementError;
+use crate::selection::SelectionError;
+use crate::stats::GenerationStats;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+/// The data sets a run is trained and evaluated on
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DataSplits {
+    pub training: DataSet,
+    pub validation: Option<DataSet>,
+    pub test: DataSet,
+}
+
+impl DataSplits {
+    /// The data sets along with their names, in the order they are reported
+    pub fn named(&self) -> Vec<(&'static str, &DataSet)> {
+        let mut data_sets = vec![("training", &self.training)];
+        if let Some(validation) = &self.validation {
+            data_sets.push(("validation", validation));
+        }
+        data_sets.push(("test", &self.test));
+        data_sets
+    }
+}
+
+/// The best candidate seen across all the generations of a run
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Champion {
+    pub candidate: Candidate,
+    pub fitness: Fitness,
+    /// The generation the candidate was found in
+    pub generation: usize,
+}
+
+/// Everything that is carried over from one generation to the next
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GaState {
+    pub population: Population,
+    pub champion: Option<Champion>,
+    /// The statistics of the generations that are done
+    pub generations: Vec<GenerationStats>,
+    pub optimum_generation: Option<usize>,
+    #[serde(default)]
+    pub fitness_cache: FitnessCache,
+}
+
+impl GaState {
+    /// The state of a run that has not started yet
+    pub fn generate<T: Rng>(rng: &mut T, ga_specs: &GaSpec) -> Self {
+        // The population starts out in generation 1, which is where the summaries of repeated
+        // runs count the generations from
+        GaState {
+            population: Population::generate(rng, ga_specs),
+            champion: None,
+            generations: Vec::with_capacity(ga_specs.max_evolutions),
+            optimum_generation: None,
+            fitness_cache: FitnessCache::new(ga_specs.fitness_cache_size),
+        }
+    }
+}
+
+/// What a run leaves behind
+#[derive(Debug, Clone)]
+pub struct RunResult {
+    pub champion: Champion,
+    /// The state of each generation, starting from the first
+    pub generations: Vec<GenerationStats>,
+    /// The first generation where a candidate classified all of the training data correctly
+    pub optimum_generation: Option<usize>,
+    /// The candidates of the last generation that was reported that no other candidate dominates
+    /// on the objectives of the spec
+    pub pareto_front: Vec<ParetoMember>,
+}
+
+#[derive(Error, Debug)]
+pub enum GaError {
+    #[error(transparent)]
+    FitnessCalculationError(#[from] FitnessCalculationError),
+
+    #[error(transparent)]
+    SelectionError(#[from] SelectionError),
+
+    #[error(transparent)]
+    CrossoverError(#[from] CrossoverError),
+
+    #[error(transparent)]
+    MutationError(#[from] MutationError),
+
+    #[error(transparent)]
+    ReplacementError(#[from] ReplacementError),
+
+    #[error("population is empty")]
+    EmptyPopulation,
+
+    #[error(transparent)]
+    CheckpointError(#[from] CheckpointError),
+}
+
+/// Formats a ratio that might not exist
+pub fn format_ratio(ratio: Option<f64>) -> String {
+    match ratio {
+        Some(ratio) => format!("{:.4}", ratio),
+        None => "n/a".to_owned(),
+    }
+}
+
+/// Evolves a population on the training data, printing out the state of every generation.
+/// Returns the best candidate that was found along with the progress of the run
+pub fn run<T: Rng>(
+    rng: &mut T,
+    ga_specs: &GaSpec,
+    data_splits: &DataSplits,
+) -> Result<RunResult, GaError> {
+    let state = GaState::generate(rng, ga_specs);
+    resume(rng, ga_specs, data_splits, state, |_, _| Ok(()))
+}
+
+/// Carries on evolving from a state until `max_evolutions` generations are done.
+/// `on_generation` is called with the state and the rng between generations, which is where a
+/// checkpoint can be taken
+pub fn resume<T, F>(
+    rng: &mut T,
+    ga_specs: &GaSpec,
+    data_splits: &DataSplits,
+    mut state: GaState,
+    mut on_generation: F,
+) -> Result<RunResult, GaError>
+where
+    T: Rng,
+    F: FnMut(&GaState, &T) -> Result<(), GaError>,
+{
+    let training = &data_splits.training;
+    let optimum = ga_specs.fitness.function.optimum(training);
+    let mut pareto_front = vec![];
+
+    while state.generations.len() < ga_specs.max_evolutions {
+        let (hits, misses) = (state.fitness_cache.hits(), state.fitness_cache.misses());
+        let fitness =
+            state
+                .population
+                .calculate_fitness(training, ga_specs, &mut state.fitness_cache)?;
+
+        let mut max = None;
+        let mut min = None;
+        let mut total = 0.0;
+
+        for CandidateFitness { fitness, .. } in &fitness {
+            match &max {
+                Some(max_fitness) => {
+                    if fitness > max_fitness {
+                        max = Some(*fitness)
+                    }
+                }
+                None => max = Some(*fitness),
+            }
+
+            match &min {
+                Some(min_fitness) => {
+                    if fitness < min_fitness {
+                        min = Some(*fitness)
+                    }
+                }
+                None => min = Some(*fitness),
+            }
+
+            total += fitness.value();
+        }
+
+        let (max, min) = match (max, min) {
+            (Some(max), Some(min)) => (max, min),
+            _ => return Err(GaError::EmptyPopulation),
+        };
+
+        let average: f64 = total / fitness.len() as f64;
+        println!("generation: {}", state.population.generation());
+        println!("population.size={}", state.population.len());
+        println!("population.averageFitness={}", average);
+        println!("population.maxFitness={}", max);
+        println!("population.minFitness={}", min);
+        let average_rules = metrics::mean(
+            &fitness
+                .iter()
+                .map(|candidate_fitness| candidate_fitness.candidate.rules().len() as f64)
+                .collect::<Vec<f64>>(),
+        )
+        .unwrap_or_default();
+        let average_constraints = metrics::mean(
+            &fitness
+                .iter()
+                .map(|candidate_fitness| candidate_fitness.candidate.constraint_count() as f64)
+                .collect::<Vec<f64>>(),
+        )
+        .unwrap_or_default();
+        println!("population.averageRules={}", average_rules);
+        println!("population.averageConstraints={}", average_constraints);
+
+        let fitness_values: Vec<f64> = fitness
+            .iter()
+            .map(|candidate_fitness| candidate_fitness.fitness.value())
+            .collect();
+        let size = state.population.len();
+        let diversity = state.population.diversity().unwrap_or_default();
+
+        // Candidates are sorted by fitness, so the best one is the last
+        let best = fitness.last().ok_or(GaError::EmptyPopulation)?;
+        for (name, data_set) in data_splits.named() {
+            println!(
+                "best.{}Accuracy={}",
+                name,
+                format_ratio(best.candidate.accuracy(data_set)?)
+            );
+        }
+        let test_accuracy = best.candidate.accuracy(&data_splits.test)?;
+
+        // Parsimony pressure can make a smaller candidate fitter than one that classifies all of
+        // the training data correctly, so the optimum is judged by the score before the penalty
+        let is_optimum = fitness
+            .iter()
+            .any(|candidate_fitness| candidate_fitness.fitness.score() >= optimum);
+        if is_optimum && state.optimum_generation.is_none() {
+            state.optimum_generation = Some(state.population.generation());
+        }
+        let is_last = (is_optimum && ga_specs.stop_at_optimum_fitness)
+            || state.generations.len() + 1 >= ga_specs.max_evolutions;
+        // The front is taken from the last generation that is reported, before the offsprings
+        // and mutants of the next one are added
+        if is_last {
+            pareto_front = pareto::front(&fitness, &ga_specs.objectives)
+                .iter()
+                .map(ParetoMember::from)
+                .collect();
+        }
+
+        let is_new_champion = match &state.champion {
+            Some(champion) => best.fitness > champion.fitness,
+            None => true,
+        };
+        if is_new_champion {
+            state.champion = Some(Champion {
+                candidate: best.candidate.clone(),
+                fitness: best.fitness,
+                generation: state.population.generation(),
+            });
+        }
+
+        let elites = match &ga_specs.elitism {
+            Some(elitism) => {
+                let elites = elitism.select(&fitness);
+                let elite_fitness: Vec<Fitness> =
+                    elites.iter().map(|elite| elite.fitness).collect();
+                println!("population.eliteFitness={:?}", elite_fitness);
+                for elite in &elites {
+                    println!("elite.rules={:?}", elite.candidate.rules());
+                    println!("elite.defaultOutput={}", elite.candidate.default_output());
+                }
+                elitism::to_owned_candidates(&elites)
+            }
+            None => vec![],
+        };
+
+        let selection = ga_specs
+            .selection
+            .select(rng, &fitness, &ga_specs.objectives)?;
+        println!("{} candidates selected for crossover", selection.len());
+        let offsprings = ga_specs.crossover.crossover(rng, &selection, ga_specs)?;
+        println!("{} new offsprings", offsprings.len());
+        let selected = selection.len();
+        let offspring_count = offsprings.len();
+        match &ga_specs.replacement {
+            Some(replacement) => {
+                let replaced = replacement.replace(
+                    &mut state.population,
+                    offsprings,
+                    &elites,
+                    training,
+                    ga_specs,
+                    &mut state.fitness_cache,
+                )?;
+                println!("{} candidates replaced", replaced);
+            }
+            None => {
+                state.population.append(offsprings);
+            }
+        }
+        let mutations = ga_specs
+            .mutation
+            .mutate(rng, &mut state.population, &elites, ga_specs)?;
+        println!("{} candidates mutated", mutations);
+
+        // Replacement looks up the fitness of the offsprings too, so the cache is only read once
+        // the generation is done with it
+        let cache_hits = state.fitness_cache.hits() - hits;
+        let cache_misses = state.fitness_cache.misses() - misses;
+        println!("fitnessCache.hits={}", cache_hits);
+        println!("fitnessCache.misses={}", cache_misses);
+
+        state.generations.push(GenerationStats {
+            generation: state.population.generation(),
+            size,
+            min_fitness: min.value(),
+            average_fitness: average,
+            max_fitness: max.value(),
+            median_fitness: metrics::median(&fitness_values).unwrap_or_default(),
+            fitness_stddev: metrics::standard_deviation(&fitness_values).unwrap_or_default(),
+            diversity,
+            selected,
+            offsprings: offspring_count,
+            mutations,
+            average_rules,
+            average_constraints,
+            cache_hits,
+            cache_misses,
+            test_accuracy,
+        });
+
+        if is_optimum && ga_specs.stop_at_optimum_fitness {
+            break;
+        }
+        state.population.increment_generation();
+        on_generation(&state, rng)?;
+    }
+
+    Ok(RunResult {
+        champion: state.champion.ok_or(GaError::EmptyPopulation)?,
+        generations: state.generations,
+        optimum_generation: state.optimum_generation,
+        pareto_front,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::population::CandidateSet;
+    use crate::rule::{Constraint, Rule};
+    use rand::SeedableRng;
+    use rand_pcg::Pcg64;
+    use std::collections::BTreeMap;
+
+    fn data_splits(path: &str) -> DataSplits {
+        let data_set = DataSet::from_file(path).unwrap();
+        DataSplits {
+            training: data_set.clone(),
+            validation: None,
+            test: data_set,
+        }
+    }
+
+    #[test]
+    fn test_reproducible_run() {
+        let data_splits = data_splits("data/data1.txt");
+        let mut ga_specs = GaSpec::for_tests(&data_splits.training);
+        ga_specs.max_evolutions = 10;
+        ga_specs.stop_at_optimum_fitness = false;
+
+        let first = run(&mut Pcg64::seed_from_u64(7), &ga_specs, &data_splits).unwrap();
+        let second = run(&mut Pcg64::seed_from_u64(7), &ga_specs, &data_splits).unwrap();
+        assert_eq!(first.generations.len(), 10);
+        assert_eq!(first.generations, second.generations);
+        assert_eq!(first.champion, second.champion);
+
+        // Spreading the fitness over threads gives the same run as calculating it on one
+        ga_specs.threads = 3;
+        let threaded = run(&mut Pcg64::seed_from_u64(7), &ga_specs, &data_splits).unwrap();
+        assert_eq!(first.generations, threaded.generations);
+        assert_eq!(first.champion, threaded.champion);
+    }
+
+    #[test]
+    fn test_split_accuracy() {
+        let data_set = |lines: &[&str]| {
+            let mut data_set = DataSet::new();
+            for line in lines {
+                data_set.push(line.parse().unwrap()).unwrap();
+            }
+            data_set
+        };
+        // The test split disagrees with the training split on most of the labels
+        let data_splits = DataSplits {
+            training: data_set(&["0 0", "1 1"]),
+            validation: None,
+            test: data_set(&["0 1", "1 1", "1 0", "0 1"]),
+        };
+        let ga_specs = GaSpec::for_tests(&data_splits.training);
+
+        let mut constraints = BTreeMap::new();
+        constraints.insert(0, Constraint::Character('1'));
+        let best =
+            Candidate::from_rules(vec![Rule::new(constraints, "1".to_owned())], "0".to_owned());
+        let mut rng = Pcg64::seed_from_u64(1);
+        let mut state = GaState::generate(&mut rng, &ga_specs);
+        let mut candidates = CandidateSet::default();
+        candidates.insert(best.clone());
+        candidates.insert(Candidate::from_rules(vec![], "0".to_owned()));
+        *state.population.candidates_mut() = candidates;
+
+        let result = resume(&mut rng, &ga_specs, &data_splits, state, |_, _| Ok(())).unwrap();
+        assert_eq!(result.champion.candidate, best);
+        assert_eq!(result.generations.last().unwrap().test_accuracy, Some(0.25));
+        assert_eq!(
+            result.champion.candidate.accuracy(&data_splits.training),
+            Ok(Some(1.0))
+        );
+    }
+
+    #[test]
+    fn test_optimum_under_parsimony() {
+        let mut data_set = DataSet::new();
+        for line in &["1 1", "0 0"] {
+            data_set.push(line.parse().unwrap()).unwrap();
+        }
+        let data_splits = DataSplits {
+            training: data_set.clone(),
+            validation: None,
+            test: data_set,
+        };
+        let mut ga_specs = GaSpec::for_tests(&data_splits.training);
+        ga_specs.max_evolutions = 5;
+        ga_specs.fitness = serde_json::from_value(serde_json::json!({
+            "type": "correct",
+            "parsimony": { "type": "linear", "per_rule": 1.0 }
+        }))
+        .unwrap();
+
+        let rule = |value: char, output: &str| {
+            let mut constraints = BTreeMap::new();
+            constraints.insert(0, Constraint::Character(value));
+            Rule::new(constraints, output.to_owned())
+        };
+        // Classifies everything correctly, but the penalty for its rules brings it down to zero
+        let optimal = Candidate::from_rules(
+            vec![rule('1', "1"), rule('0', "0"), rule('0', "0")],
+            "0".to_owned(),
+        );
+        // Gets one item right with no rules, which makes it the fittest
+        let small = Candidate::from_rules(vec![], "0".to_owned());
+
+        let mut rng = Pcg64::seed_from_u64(1);
+        let mut state = GaState::generate(&mut rng, &ga_specs);
+        let mut candidates = CandidateSet::default();
+        candidates.insert(optimal);
+        candidates.insert(small.clone());
+        *state.population.candidates_mut() = candidates;
+
+        let result = resume(&mut rng, &ga_specs, &data_splits, state, |_, _| Ok(())).unwrap();
+        assert_eq!(result.champion.candidate, small);
+        assert_eq!(result.optimum_generation, Some(1));
+        assert_eq!(result.generations.len(), 1);
+        // The front comes from the generation that was reported, not from the offsprings bred
+        // out of it
+        let front: Vec<&Candidate> = result
+            .pareto_front
+            .iter()
+            .map(|member| &member.candidate)
+            .collect();
+        assert_eq!(front, vec![&small]);
+    }
+}
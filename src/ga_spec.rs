@@ -1,6 +1,11 @@
 use crate::crossover::CrossoverStrategy;
+use crate::dataset::DataSet;
+use crate::elitism::ElitismStrategy;
+use crate::fitness::FitnessSpec;
 use crate::mutation::MutationStrategy;
-use crate::selection::SelectionStrategy;
+use crate::pareto::{self, Objective};
+use crate::replacement::{ReplacementStrategy, ReplacementStrategyVariant};
+use crate::selection::{SelectionStrategy, SelectionStrategyVariant};
 use serde::{Deserialize, Serialize};
 use std::fs;
 use std::path::Path;
@@ -9,11 +14,20 @@ use thiserror::Error;
 #[derive(Debug)]
 pub struct GaSpec {
     pub initial_generation: InitialGenerationSpec,
+    pub seed: Option<u64>,
+    pub unique_rules: bool,
+    pub representation: RepresentationSpec,
+    pub threads: usize,
+    pub fitness_cache_size: usize,
     pub max_evolutions: usize,
     pub stop_at_optimum_fitness: bool,
+    pub fitness: FitnessSpec,
+    pub objectives: Vec<Objective>,
     pub selection: SelectionStrategy,
     pub crossover: CrossoverStrategy,
     pub mutation: MutationStrategy,
+    pub replacement: Option<ReplacementStrategy>,
+    pub elitism: Option<ElitismStrategy>,
     pub calculated: CalculatedSpecs,
 }
 
@@ -21,25 +35,58 @@ impl From<(GaSpecInput, CalculatedSpecs)> for GaSpec {
     fn from((ga_spec_input, calculated): (GaSpecInput, CalculatedSpecs)) -> Self {
         let GaSpecInput {
             initial_generation,
+            seed,
+            unique_rules,
+            representation,
+            threads,
+            fitness_cache_size,
             max_evolutions,
             stop_at_optimum_fitness,
+            fitness,
+            objectives,
             selection,
             crossover,
             mutation,
+            replacement,
+            elitism,
         } = ga_spec_input;
 
         GaSpec {
             initial_generation,
+            seed,
+            unique_rules,
+            representation,
+            threads,
+            fitness_cache_size,
             max_evolutions,
             stop_at_optimum_fitness,
+            fitness,
+            objectives,
             selection,
             crossover,
             mutation,
+            replacement,
+            elitism,
             calculated,
         }
     }
 }
 
+impl GaSpec {
+    /// Whether selection or replacement optimises for the objectives rather than the fitness
+    /// alone
+    pub fn is_multi_objective(&self) -> bool {
+        let nsga2_replacement = matches!(
+            &self.replacement,
+            Some(ReplacementStrategy {
+                variant: ReplacementStrategyVariant::Nsga2,
+                ..
+            })
+        );
+        matches!(self.selection.variant, SelectionStrategyVariant::Nsga2(_)) || nsga2_replacement
+    }
+}
+
 #[derive(Error, Debug)]
 pub enum GaSpecInputParseError {
     #[error("an io error occured")]
@@ -56,32 +103,158 @@ impl GaSpecInput {
         let ga_spec = serde_json::from_str(&config)?;
         Ok(ga_spec)
     }
+
+    pub fn seed(&self) -> Option<u64> {
+        self.seed
+    }
+
+    pub fn set_seed(&mut self, seed: u64) {
+        self.seed = Some(seed);
+    }
+
+    pub fn representation(&self) -> RepresentationSpec {
+        self.representation
+    }
+
+    pub fn set_threads(&mut self, threads: usize) {
+        self.threads = threads;
+    }
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone)]
 pub struct CalculatedSpecs {
     pub alphabet: &'static str,
+    /// The classes found in the data. These are the outputs a rule can predict
+    pub classes: Vec<String>,
     pub max_index: usize,
+    /// The smallest and the largest value of each feature in the training data
+    pub value_ranges: Vec<(f64, f64)>,
 }
 
-#[derive(Debug, Clone, Deserialize)]
+impl CalculatedSpecs {
+    /// Calculates the specs from the training data. Returns none if there is no training data
+    pub fn from_training(training: &DataSet, classes: Vec<String>) -> Option<Self> {
+        Some(CalculatedSpecs {
+            alphabet: "01",
+            classes,
+            max_index: training.width()?,
+            value_ranges: training.value_ranges()?,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GaSpecInput {
     initial_generation: InitialGenerationSpec,
+    /// Seed for the random number generator. A random seed is used when missing
+    #[serde(default)]
+    seed: Option<u64>,
+    /// Whether or not a candidate can contain the same rule more than once
+    #[serde(default = "default_unique_rules")]
+    unique_rules: bool,
+    #[serde(default)]
+    representation: RepresentationSpec,
+    /// Number of threads the fitness of the population is calculated on
+    #[serde(default = "default_threads")]
+    threads: usize,
+    /// Number of candidates whose fitness is remembered between generations. Zero turns the cache
+    /// off
+    #[serde(default = "default_fitness_cache_size")]
+    fitness_cache_size: usize,
     max_evolutions: usize,
     stop_at_optimum_fitness: bool,
+    /// How the fitness of a candidate is calculated. Defaults to the number of items classified
+    /// correctly
+    #[serde(default)]
+    fitness: FitnessSpec,
+    /// What NSGA-II selection and replacement trade off against each other, and what the exported
+    /// Pareto front is made of. Defaults to fitness, rules and constraints
+    #[serde(default = "pareto::default_objectives")]
+    objectives: Vec<Objective>,
     selection: SelectionStrategy,
     crossover: CrossoverStrategy,
     mutation: MutationStrategy,
+    #[serde(default)]
+    replacement: Option<ReplacementStrategy>,
+    #[serde(default)]
+    elitism: Option<ElitismStrategy>,
+}
+
+fn default_unique_rules() -> bool {
+    true
 }
 
-#[derive(Debug, Clone, Deserialize)]
+fn default_threads() -> usize {
+    1
+}
+
+fn default_fitness_cache_size() -> usize {
+    1024
+}
+
+/// How the constraints within a rule are represented
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(try_from = "RepresentationSpecShadow")]
+#[serde(tag = "type")]
+#[serde(rename_all = "snake_case")]
+pub enum RepresentationSpec {
+    /// Constraints are characters from the alphabet. Real valued data is rounded to binary digits
+    #[default]
+    Binary,
+    /// Constraints are `[lower, upper]` intervals on real valued features.
+    /// The widths are fractions of the range of values each feature has in the training data
+    Interval { min_width: f64, max_width: f64 },
+}
+
+#[derive(Deserialize, Debug, Clone, Copy)]
+#[serde(tag = "type")]
+#[serde(rename_all = "snake_case")]
+enum RepresentationSpecShadow {
+    Binary,
+    Interval { min_width: f64, max_width: f64 },
+}
+
+#[derive(Error, Debug)]
+pub enum RepresentationSpecParseError {
+    #[error("interval widths cannot be negative")]
+    NegativeWidth,
+    #[error("max width is less than min width")]
+    MaxWidthIsLessThanMinWidth,
+}
+
+impl std::convert::TryFrom<RepresentationSpecShadow> for RepresentationSpec {
+    type Error = RepresentationSpecParseError;
+    fn try_from(shadow: RepresentationSpecShadow) -> Result<Self, Self::Error> {
+        match shadow {
+            RepresentationSpecShadow::Binary => Ok(RepresentationSpec::Binary),
+            RepresentationSpecShadow::Interval {
+                min_width,
+                max_width,
+            } => {
+                // Written so that NaN widths are refused as well
+                if !(min_width >= 0.0 && max_width >= 0.0) {
+                    return Err(RepresentationSpecParseError::NegativeWidth);
+                }
+                if min_width > max_width {
+                    return Err(RepresentationSpecParseError::MaxWidthIsLessThanMinWidth);
+                }
+                Ok(RepresentationSpec::Interval {
+                    min_width,
+                    max_width,
+                })
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct InitialGenerationSpec {
     pub candidates: InitialGenerationComponentSpec,
     pub rules: InitialGenerationComponentSpec,
     pub constraints: InitialGenerationComponentSpec,
 }
 
-#[derive(Debug, Clone, Copy, Deserialize)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 #[serde(try_from = "InitialGenerationComponentSpecShadow")]
 pub struct InitialGenerationComponentSpec {
     pub min: usize,
@@ -129,3 +302,75 @@ impl std::convert::TryFrom<InitialGenerationComponentSpecShadow>
         })
     }
 }
+
+#[cfg(test)]
+impl GaSpecInput {
+    /// A small spec for the unit tests. Written out here rather than read from input.json, which
+    /// is an example for users to edit
+    pub(crate) fn for_tests() -> GaSpecInput {
+        serde_json::from_value(serde_json::json!({
+            "initial_generation": {
+                "candidates": { "min": 10, "max": 16, "rng_fail_retries": 1000 },
+                "rules": { "min": 12, "max": 20, "rng_fail_retries": 1000 },
+                "constraints": { "min": 3, "max": 5, "rng_fail_retries": 1000 }
+            },
+            "max_evolutions": 20,
+            "stop_at_optimum_fitness": true,
+            "selection": {
+                "type": "tournament",
+                "tournament_size": 2,
+                "selection_size": 2,
+                "duplicates": { "setting": "allow" }
+            },
+            "crossover": {
+                "matchup_strategy": { "type": "next_fittest" },
+                "mating_strategy": { "type": "single_point_at_percentage", "split_at": 50 },
+                "mirroring": { "setting": "mirror_if_asexual" },
+                "crossover_rate": 50
+            },
+            "mutation": {
+                "type": "constraint_value_randomize",
+                "chance": 8,
+                "chance_per_candidate": 8,
+                "chance_per_rule": 100,
+                "chance_per_constraint": 8,
+                "chance_per_output": 8
+            },
+            "replacement": { "type": "steady_state", "population_size": 16 },
+            "elitism": { "type": "count", "count": 1 }
+        }))
+        .unwrap()
+    }
+}
+
+#[cfg(test)]
+impl GaSpec {
+    /// The spec of `GaSpecInput::for_tests`, calculated from the training data
+    pub(crate) fn for_tests(training: &DataSet) -> GaSpec {
+        let calculated = CalculatedSpecs::from_training(training, training.classes()).unwrap();
+        (GaSpecInput::for_tests(), calculated).into()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_interval_widths() {
+        let parse = |json: &str| serde_json::from_str::<RepresentationSpec>(json);
+        assert_eq!(
+            parse(r#"{"type": "interval", "min_width": 0.1, "max_width": 0.5}"#).unwrap(),
+            RepresentationSpec::Interval {
+                min_width: 0.1,
+                max_width: 0.5
+            }
+        );
+        assert_eq!(
+            parse(r#"{"type": "binary"}"#).unwrap(),
+            RepresentationSpec::Binary
+        );
+        assert!(parse(r#"{"type": "interval", "min_width": -0.1, "max_width": 0.5}"#).is_err());
+        assert!(parse(r#"{"type": "interval", "min_width": 0.5, "max_width": 0.1}"#).is_err());
+    }
+}
@@ -0,0 +1,24 @@
+#[deny(missing_copy_implementations, missing_debug_implementations)]
+pub mod arff_dataset;
+pub mod bits;
+pub mod candidate;
+pub mod checkpoint;
+pub mod crossover;
+pub mod csv_dataset;
+pub mod dataitem;
+pub mod dataset;
+pub mod elitism;
+pub mod fitness;
+pub mod fitness_cache;
+pub mod ga;
+pub mod ga_spec;
+pub mod metrics;
+pub mod model;
+pub mod mutation;
+pub mod pareto;
+pub mod population;
+pub mod replacement;
+pub mod rule;
+pub mod runs;
+pub mod selection;
+pub mod stats;
@@ -1,32 +1,27 @@
-use serde::{Deserialize, Serialize};
-use serde_json;
-use std::path::PathBuf;
+use rand::{Rng, SeedableRng};
+use rand_pcg::Pcg64;
+use std::ffi::OsString;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
 use structopt::StructOpt;
 use thiserror::Error;
-#[deny(missing_copy_implementations, missing_debug_implementations)]
-mod candidate;
-mod crossover;
-mod dataitem;
-mod dataset;
-mod ga_spec;
-mod mutation;
-mod population;
-mod rule;
-mod selection;
-
-use candidate::CandidateFitness;
-use crossover::{
-    CrossoverStrategy, CrossoverStrategyCommonOptions, MatchupStrategy, MatingStrategy,
-    MirroringStrategy,
-};
-use mutation::{MutationStrategy, MutationStrategyCommonOptions, MutationStrategyVariant};
 
-use ga_spec::{CalculatedSpecs, GaSpec, GaSpecInput};
-use population::Population;
-use selection::{
-    DuplicateHandlingStrategy, RouletteSelection, Selection, SelectionStrategy,
-    SelectionStrategyCommonOptions, SelectionStrategyVariant, TournamentSelection,
+use biocomputation::arff_dataset::{self, ArffOptions};
+use biocomputation::checkpoint::{Checkpoint, CHECKPOINT_VERSION};
+use biocomputation::csv_dataset::{self, CsvOptions};
+use biocomputation::dataitem::Row;
+use biocomputation::dataset::{
+    self, DataFormat, DataSet, DataSetError, DataSetParseError, SplitStrategy, TargetColumn,
 };
+use biocomputation::ga::{self, format_ratio, Champion, DataSplits, GaError, GaState, RunResult};
+use biocomputation::ga_spec::{CalculatedSpecs, GaSpec, GaSpecInput, RepresentationSpec};
+use biocomputation::metrics::{self, Evaluation};
+use biocomputation::model::{self, Model};
+use biocomputation::runs::{RunRecord, RunsSummary};
+use biocomputation::stats::{StatsError, StatsFormat, StatsWriter};
+
+type StatsFile = StatsWriter<BufWriter<File>>;
 
 #[derive(Error, Debug)]
 pub enum PercentageParseError {
@@ -40,6 +35,22 @@ pub enum PercentageParseError {
     CannotSplitAtZero,
 }
 
+#[derive(Error, Debug)]
+pub enum RunError {
+    #[error("the training data set is empty")]
+    EmptyTrainingSet,
+
+    #[error("the data has {found} features but the model expects {expected}")]
+    ModelWidthMismatch { expected: usize, found: usize },
+
+    #[error("row {row} has {found} features but the model expects {expected}")]
+    WidthMismatch {
+        row: usize,
+        expected: usize,
+        found: usize,
+    },
+}
+
 fn parse_percentage(src: &str) -> Result<f64, PercentageParseError> {
     let value: f64 = src.parse()?;
     if value > 100.0 {
@@ -55,90 +66,791 @@ fn parse_percentage(src: &str) -> Result<f64, PercentageParseError> {
 
 #[derive(StructOpt, Debug)]
 #[structopt(name = "biocomputation_ga")]
-struct Opt {
+enum Opt {
+    /// Evolves a rule set on a data set. Runs when no subcommand is given
+    Train(Box<TrainOpt>),
+    /// Applies an exported model to new data. The label column is optional
+    Predict(PredictOpt),
+    /// Reports a confusion matrix and metrics of an exported model on labeled data
+    Evaluate(EvaluateOpt),
+}
+
+#[derive(StructOpt, Debug)]
+struct TrainOpt {
     #[structopt(short,long, parse(try_from_str = parse_percentage), default_value="50.0")]
     split_percentage: f64,
 
+    #[structopt(long, parse(from_os_str), required_unless = "resume")]
+    spec: Option<PathBuf>,
+
+    /// How the data set is split: ordered, shuffled or stratified. Shuffling uses the seed
+    #[structopt(long, default_value = "ordered")]
+    split_strategy: SplitStrategy,
+
+    /// Percentage of the whole data set held out as a validation set. The validation set is taken
+    /// out of what remains after the training set
+    #[structopt(long, parse(try_from_str = parse_percentage))]
+    validation_percentage: Option<f64>,
+
+    /// Runs k-fold cross validation instead of a single split. The data set is divided into k
+    /// stratified folds and the GA is run once per fold, testing on that fold
+    #[structopt(long, conflicts_with = "validation-percentage")]
+    folds: Option<usize>,
+
+    /// Repeats the GA this many times on the same split, seeding the n-th run with the seed plus
+    /// n, and reports statistics of the best fitness across the runs
+    #[structopt(long, conflicts_with = "folds")]
+    runs: Option<usize>,
+
+    /// Writes the statistics of the repeated runs to this file as JSON
+    #[structopt(long, parse(from_os_str), requires = "runs")]
+    runs_output: Option<PathBuf>,
+
+    /// Writes statistics of every generation to this file. Repeated runs and folds are told apart
+    /// by the run column
+    #[structopt(long, parse(from_os_str))]
+    output_stats: Option<PathBuf>,
+
+    /// Format of the statistics file: csv or jsonl. Guessed from the extension when missing
+    #[structopt(long)]
+    stats_format: Option<StatsFormat>,
+
+    /// Writes the champion to this file as a JSON model that can be used to predict
+    #[structopt(long, parse(from_os_str), conflicts_with_all = &["folds", "runs"])]
+    export_model: Option<PathBuf>,
+
+    /// Writes the candidates on the Pareto front of the final population to this file as a JSON
+    /// list of models, one per candidate
+    #[structopt(long, parse(from_os_str), conflicts_with_all = &["folds", "runs"])]
+    export_pareto_front: Option<PathBuf>,
+
+    /// Seed for the random number generator. Overrides the seed in the spec
+    #[structopt(long)]
+    seed: Option<u64>,
+
+    /// Number of threads the fitness of the population is calculated on. Overrides the number in
+    /// the spec. The result is the same whatever the number of threads
+    #[structopt(long)]
+    threads: Option<usize>,
+
+    /// Periodically writes the whole state of the run to this file so that it can be resumed
+    #[structopt(long, parse(from_os_str), conflicts_with_all = &["folds", "runs"])]
+    checkpoint: Option<PathBuf>,
+
+    /// Number of generations between two checkpoints
+    #[structopt(long, default_value = "10")]
+    checkpoint_interval: usize,
+
+    /// Carries on a run from a checkpoint. The spec, the seed and the data are taken from the
+    /// checkpoint
+    #[structopt(
+        long,
+        parse(from_os_str),
+        conflicts_with_all = &["spec", "seed", "folds", "runs", "FILE"]
+    )]
+    resume: Option<PathBuf>,
+
+    #[structopt(flatten)]
+    data: DataOpt,
+
+    #[structopt(name = "FILE", parse(from_os_str), required_unless = "resume")]
+    path: Option<PathBuf>,
+}
+
+/// Where the data comes from and how it is read
+#[derive(StructOpt, Debug)]
+struct DataOpt {
+    /// Format of the data file: text, csv or arff. Guessed from the extension when missing
+    #[structopt(long)]
+    format: Option<DataFormat>,
+
+    /// Delimiter between the values of a csv file
+    #[structopt(long, default_value = ",")]
+    delimiter: char,
+
+    /// The first line of the csv file is a record rather than the names of the columns
+    #[structopt(long)]
+    no_header: bool,
+
+    /// The column(name or zero based index) of a csv or arff file that holds the class.
+    /// Defaults to the last column
+    #[structopt(long)]
+    target: Option<TargetColumn>,
+}
+
+#[derive(StructOpt, Debug)]
+struct EvaluateOpt {
+    /// A model exported by the train subcommand
+    #[structopt(long, parse(from_os_str))]
+    model: PathBuf,
+
+    /// Writes the report as JSON instead of text
+    #[structopt(long)]
+    json: bool,
+
+    /// Writes the report to this file instead of stdout
     #[structopt(long, parse(from_os_str))]
-    spec: PathBuf,
+    output: Option<PathBuf>,
+
+    #[structopt(flatten)]
+    data: DataOpt,
 
     #[structopt(name = "FILE", parse(from_os_str))]
-    data: PathBuf,
+    path: PathBuf,
+}
+
+#[derive(StructOpt, Debug)]
+struct PredictOpt {
+    /// A model exported by the train subcommand
+    #[structopt(long, parse(from_os_str))]
+    model: PathBuf,
+
+    /// Writes the predictions to this file instead of stdout
+    #[structopt(long, parse(from_os_str))]
+    output: Option<PathBuf>,
+
+    /// Adds the index of the rule that fired for each row. Left empty when the default output
+    /// was used
+    #[structopt(long)]
+    show_rule: bool,
+
+    #[structopt(flatten)]
+    data: DataOpt,
+
+    #[structopt(name = "FILE", parse(from_os_str))]
+    path: PathBuf,
+}
+
+fn csv_options(opt: &DataOpt) -> CsvOptions {
+    CsvOptions {
+        delimiter: opt.delimiter,
+        has_header: !opt.no_header,
+        target: opt.target.clone(),
+    }
+}
+
+fn arff_options(opt: &DataOpt) -> ArffOptions {
+    ArffOptions {
+        target: opt.target.clone(),
+    }
+}
+
+fn data_format(opt: &DataOpt, path: &Path) -> DataFormat {
+    opt.format.unwrap_or_else(|| DataFormat::from_path(path))
+}
+
+fn load_data_set(opt: &DataOpt, path: &Path) -> Result<DataSet, DataSetParseError> {
+    match data_format(opt, path) {
+        DataFormat::Text => DataSet::from_file(path),
+        DataFormat::Csv => DataSet::from_csv_file(path, &csv_options(opt)),
+        DataFormat::Arff => DataSet::from_arff_file(path, &arff_options(opt)),
+    }
+}
+
+/// Loads inputs with `width` features, where the label may be missing
+fn load_rows(opt: &DataOpt, path: &Path, width: usize) -> Result<Vec<Row>, DataSetParseError> {
+    match data_format(opt, path) {
+        DataFormat::Text => dataset::rows_from_file(path),
+        DataFormat::Csv => csv_dataset::rows_from_csv_file(path, &csv_options(opt), width),
+        DataFormat::Arff => arff_dataset::rows_from_arff_file(path, &arff_options(opt), width),
+    }
+}
+
+/// Training was all the program did before it had subcommands, so arguments that do not start
+/// with one are taken as the arguments of `train`
+fn with_default_subcommand(mut args: Vec<OsString>) -> Vec<OsString> {
+    let first = args.get(1).map(|arg| arg.to_string_lossy().into_owned());
+    let names_subcommand = matches!(
+        first.as_deref(),
+        None | Some(
+            "train" | "predict" | "evaluate" | "help" | "-h" | "--help" | "-V" | "--version"
+        )
+    );
+    if !names_subcommand {
+        args.insert(1, "train".into());
+    }
+    args
 }
 
 fn main() {
-    if let Err(err) = run_ga() {
+    let args = with_default_subcommand(std::env::args_os().collect());
+    let result = match Opt::from_iter(args) {
+        Opt::Train(opt) => run_ga(*opt),
+        Opt::Predict(opt) => predict(opt),
+        Opt::Evaluate(opt) => evaluate(opt),
+    };
+    if let Err(err) = result {
         println!("program exited due to error: {}", err);
     }
 }
-fn run_ga() -> Result<(), Box<dyn std::error::Error>> {
-    let opt = Opt::from_args();
-    let ga_specs_input = GaSpecInput::from_file(opt.spec)?;
-    let data_set = dataset::DataSet::from_file(opt.data)?;
-    let (training, test) = data_set.split_at_percentage(opt.split_percentage)?;
 
-    let width = training.width().expect("no training data");
-    let alphabet = "01";
-    let calculated = CalculatedSpecs {
-        alphabet,
-        max_index: width,
+/// Opens the file to write to, or stdout if there is none
+fn open_output<'a>(
+    path: Option<&PathBuf>,
+    stdout: &'a io::Stdout,
+) -> io::Result<Box<dyn Write + 'a>> {
+    Ok(match path {
+        Some(path) => Box::new(BufWriter::new(File::create(path)?)),
+        None => Box::new(stdout.lock()),
+    })
+}
+
+/// Writes how well the model does on a labeled data set
+fn evaluate(opt: EvaluateOpt) -> Result<(), Box<dyn std::error::Error>> {
+    let model = Model::from_file(&opt.model)?;
+    let data_set = load_data_set(&opt.data, &opt.path)?;
+    let data_set = match model.representation {
+        RepresentationSpec::Binary => data_set.binarize(),
+        RepresentationSpec::Interval { .. } => data_set,
     };
-    let ga_specs = (ga_specs_input, calculated).into();
+    let width = data_set.width().unwrap_or_default();
+    if width != model.feature_width {
+        return Err(Box::new(RunError::ModelWidthMismatch {
+            expected: model.feature_width,
+            found: width,
+        }));
+    }
 
-    println!("{:#?}", ga_specs);
-    println!("training data set size: {}", training.len());
+    let evaluation = model.candidate.evaluate(&data_set, &model.classes)?;
+
+    let stdout = io::stdout();
+    let mut writer = open_output(opt.output.as_ref(), &stdout)?;
+    if opt.json {
+        serde_json::to_writer_pretty(&mut writer, &evaluation)?;
+        writeln!(writer)?;
+    } else {
+        write_evaluation(&mut writer, &evaluation)?;
+    }
+    writer.flush()?;
+    Ok(())
+}
 
-    let mut population = Population::generate(&ga_specs);
+fn write_evaluation(writer: &mut dyn Write, evaluation: &Evaluation) -> io::Result<()> {
+    writeln!(writer, "evaluation.size={}", evaluation.size)?;
+    for (name, value) in &[
+        ("accuracy", evaluation.accuracy),
+        ("balancedAccuracy", evaluation.balanced_accuracy),
+        ("macroPrecision", evaluation.macro_precision),
+        ("macroRecall", evaluation.macro_recall),
+        ("macroF1", evaluation.macro_f1),
+        ("mcc", evaluation.mcc),
+    ] {
+        writeln!(writer, "evaluation.{}={}", name, format_ratio(*value))?;
+    }
+
+    writeln!(
+        writer,
+        "confusion matrix(rows are actual, columns are predicted):"
+    )?;
+    let column_width = evaluation
+        .classes
+        .iter()
+        .map(String::len)
+        .chain(std::iter::once(evaluation.size.to_string().len()))
+        .max()
+        .unwrap_or_default();
+    write!(writer, "{:>width$}", "", width = column_width)?;
+    for class in &evaluation.classes {
+        write!(writer, " {:>width$}", class, width = column_width)?;
+    }
+    writeln!(writer)?;
+    for (class, row) in evaluation.classes.iter().zip(&evaluation.confusion_matrix) {
+        write!(writer, "{:>width$}", class, width = column_width)?;
+        for count in row {
+            write!(writer, " {:>width$}", count, width = column_width)?;
+        }
+        writeln!(writer)?;
+    }
+
+    for class_metrics in &evaluation.class_metrics {
+        writeln!(
+            writer,
+            "evaluation.class[{}]: support={} precision={} recall={} f1={}",
+            class_metrics.class,
+            class_metrics.support,
+            format_ratio(class_metrics.precision()),
+            format_ratio(class_metrics.recall()),
+            format_ratio(class_metrics.f1()),
+        )?;
+    }
+    for rule_metrics in &evaluation.rule_metrics {
+        let name = match rule_metrics.index {
+            Some(index) => format!("rule[{}]", index),
+            None => "default".to_owned(),
+        };
+        writeln!(
+            writer,
+            "evaluation.{}: output={} fired={} coverage={} precision={}",
+            name,
+            rule_metrics.output,
+            rule_metrics.fired,
+            format_ratio(rule_metrics.coverage),
+            format_ratio(rule_metrics.precision),
+        )?;
+    }
+    Ok(())
+}
+
+/// Writes the prediction of the model for each of the rows as csv
+fn predict(opt: PredictOpt) -> Result<(), Box<dyn std::error::Error>> {
+    let model = Model::from_file(&opt.model)?;
+    let rows = load_rows(&opt.data, &opt.path, model.feature_width)?;
+
+    let stdout = io::stdout();
+    let mut writer = open_output(opt.output.as_ref(), &stdout)?;
+
+    write!(writer, "row,label,prediction")?;
+    if opt.show_rule {
+        write!(writer, ",rule")?;
+    }
+    writeln!(writer)?;
+
+    for (index, row) in rows.into_iter().enumerate() {
+        let input = match model.representation {
+            RepresentationSpec::Binary => row.input.binarize(),
+            RepresentationSpec::Interval { .. } => row.input,
+        };
+        if input.width() != model.feature_width {
+            return Err(Box::new(RunError::WidthMismatch {
+                row: index,
+                expected: model.feature_width,
+                found: input.width(),
+            }));
+        }
+        let (rule_index, prediction) = model.candidate.classify(&input)?;
+        write!(
+            writer,
+            "{},{},{}",
+            index,
+            row.label.as_deref().unwrap_or_default(),
+            prediction
+        )?;
+        if opt.show_rule {
+            write!(
+                writer,
+                ",{}",
+                rule_index
+                    .map(|index| index.to_string())
+                    .unwrap_or_default()
+            )?;
+        }
+        writeln!(writer)?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+fn run_ga(opt: TrainOpt) -> Result<(), Box<dyn std::error::Error>> {
+    if let Some(path) = &opt.resume {
+        let mut checkpoint = Checkpoint::from_file(path)?;
+        if let Some(threads) = opt.threads {
+            checkpoint.spec.set_threads(threads);
+        }
+        println!(
+            "resuming from generation {}",
+            checkpoint.state.population.generation()
+        );
+        let ga_specs = build_specs(
+            &checkpoint.spec,
+            &checkpoint.classes,
+            &checkpoint.data_splits,
+        )?;
+        return evolve(&opt, &ga_specs, checkpoint);
+    }
+    // Both are required unless resuming
+    let (spec_path, data_path) = match (&opt.spec, &opt.path) {
+        (Some(spec_path), Some(data_path)) => (spec_path, data_path),
+        _ => unreachable!(),
+    };
 
-    population.increment_generation();
+    let spec_contents = fs::read(spec_path)?;
+    let mut ga_specs_input: GaSpecInput = serde_json::from_slice(&spec_contents)?;
+    // Always run with a known seed so that any run can be reproduced
+    let seed = opt
+        .seed
+        .or_else(|| ga_specs_input.seed())
+        .unwrap_or_else(|| rand::thread_rng().gen());
+    ga_specs_input.set_seed(seed);
+    if let Some(threads) = opt.threads {
+        ga_specs_input.set_threads(threads);
+    }
+    let mut rng = Pcg64::seed_from_u64(seed);
 
-    for i in 0..ga_specs.max_evolutions {
-        let fitness = population.calculate_fitness(&training)?;
+    let data_set = load_data_set(&opt.data, data_path)?;
+    let data_set = match ga_specs_input.representation() {
+        RepresentationSpec::Binary => data_set.binarize(),
+        RepresentationSpec::Interval { .. } => data_set,
+    };
+    let classes = data_set.classes();
 
-        let mut max = None;
-        let mut min = None;
-        let mut total = 0;
+    if let Some(folds) = opt.folds {
+        return cross_validate(
+            &mut rng,
+            &ga_specs_input,
+            &data_set,
+            &classes,
+            folds,
+            &mut open_stats(&opt)?,
+        );
+    }
 
-        for CandidateFitness { fitness, candidate } in &fitness {
-            match &max {
-                Some(max_fitness) => {
-                    if fitness > max_fitness {
-                        max = Some(*fitness)
-                    }
-                }
-                None => max = Some(*fitness),
+    let (training, rest) = data_set.split(&mut rng, opt.split_percentage, opt.split_strategy)?;
+    let (validation, test) = match opt.validation_percentage {
+        Some(validation_percentage) => {
+            let remaining_percentage = 100.0 - opt.split_percentage;
+            if validation_percentage >= remaining_percentage {
+                return Err(Box::new(DataSetError::InvalidPercentage));
             }
+            let (validation, test) = rest.split(
+                &mut rng,
+                validation_percentage / remaining_percentage * 100.0,
+                opt.split_strategy,
+            )?;
+            (Some(validation), test)
+        }
+        None => (None, rest),
+    };
+    let data_splits = DataSplits {
+        training,
+        validation,
+        test,
+    };
+
+    let ga_specs = build_specs(&ga_specs_input, &classes, &data_splits)?;
+
+    if let Some(runs) = opt.runs {
+        return repeat_runs(
+            seed,
+            &ga_specs,
+            &data_splits,
+            runs,
+            opt.runs_output.as_ref(),
+            &mut open_stats(&opt)?,
+        );
+    }
+
+    let state = GaState::generate(&mut rng, &ga_specs);
+    let checkpoint = Checkpoint {
+        version: CHECKPOINT_VERSION,
+        spec: ga_specs_input,
+        spec_hash: model::spec_hash(&spec_contents),
+        seed,
+        classes,
+        data_splits,
+        rng,
+        state,
+    };
+    evolve(&opt, &ga_specs, checkpoint)
+}
+
+/// Opens the statistics file, if one was asked for
+fn open_stats(opt: &TrainOpt) -> io::Result<Option<StatsFile>> {
+    Ok(match &opt.output_stats {
+        Some(path) => Some(StatsWriter::new(
+            BufWriter::new(File::create(path)?),
+            opt.stats_format
+                .unwrap_or_else(|| StatsFormat::from_path(path)),
+        )),
+        None => None,
+    })
+}
 
-            match &min {
-                Some(min_fitness) => {
-                    if fitness < min_fitness {
-                        min = Some(*fitness)
-                    }
-                }
-                None => min = Some(*fitness),
+/// Runs the GA from the state in the checkpoint, writing a new checkpoint every few generations
+/// if asked to
+fn evolve(
+    opt: &TrainOpt,
+    ga_specs: &GaSpec,
+    checkpoint: Checkpoint,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let Checkpoint {
+        spec,
+        spec_hash,
+        seed,
+        classes,
+        data_splits,
+        mut rng,
+        state,
+        ..
+    } = checkpoint;
+
+    let mut stats_writer = open_stats(opt)?;
+    let result = ga::resume(
+        &mut rng,
+        ga_specs,
+        &data_splits,
+        state,
+        |state: &GaState, rng: &Pcg64| {
+            let path = match &opt.checkpoint {
+                Some(path) => path,
+                None => return Ok(()),
+            };
+            if !state
+                .generations
+                .len()
+                .is_multiple_of(opt.checkpoint_interval.max(1))
+            {
+                return Ok(());
+            }
+            Checkpoint {
+                version: CHECKPOINT_VERSION,
+                spec: spec.clone(),
+                spec_hash: spec_hash.clone(),
+                seed,
+                classes: classes.clone(),
+                data_splits: data_splits.clone(),
+                rng: rng.clone(),
+                state: state.clone(),
             }
+            .to_file(path)
+            .map_err(GaError::from)?;
+            println!("checkpoint written to {}", path.display());
+            Ok(())
+        },
+    )?;
+    write_stats(&mut stats_writer, 0, &result)?;
+
+    println!("summary:");
+    print_champion("champion", &result.champion, &data_splits, &classes)?;
+
+    if let Some(path) = &opt.export_model {
+        Model::new(&result.champion, ga_specs, spec_hash.clone(), &data_splits)?.to_file(path)?;
+        println!("champion exported to {}", path.display());
+    }
+
+    // The front is taken from the last generation that was reported
+    let generation = result
+        .generations
+        .last()
+        .map(|stats| stats.generation)
+        .unwrap_or_default();
+    let mut models = Vec::with_capacity(result.pareto_front.len());
+    // The front is only of interest when it is exported or when the run optimises for it
+    let show_front = opt.export_pareto_front.is_some() || ga_specs.is_multi_objective();
+    for (index, member) in result.pareto_front.iter().enumerate() {
+        if show_front {
+            println!(
+                "paretoFront[{}]: fitness={} rules={} constraints={} testAccuracy={}",
+                index,
+                member.fitness,
+                member.rules,
+                member.constraints,
+                format_ratio(member.candidate.accuracy(&data_splits.test)?)
+            );
+        }
+        if opt.export_pareto_front.is_some() {
+            let champion = Champion {
+                candidate: member.candidate.clone(),
+                fitness: member.fitness,
+                generation,
+            };
+            models.push(Model::new(
+                &champion,
+                ga_specs,
+                spec_hash.clone(),
+                &data_splits,
+            )?);
+        }
+    }
+    if let Some(path) = &opt.export_pareto_front {
+        serde_json::to_writer_pretty(BufWriter::new(File::create(path)?), &models)?;
+        println!("pareto front exported to {}", path.display());
+    }
+    Ok(())
+}
+
+/// Runs the GA a number of times with different seeds and reports how the best fitness varies
+fn repeat_runs(
+    seed: u64,
+    ga_specs: &GaSpec,
+    data_splits: &DataSplits,
+    run_count: usize,
+    output: Option<&PathBuf>,
+    stats_writer: &mut Option<StatsFile>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut records = Vec::with_capacity(run_count);
+    for run_index in 0..run_count {
+        let run_seed = seed.wrapping_add(run_index as u64);
+        println!("run: {}", run_index);
+        println!("run.seed={}", run_seed);
+        let mut rng = Pcg64::seed_from_u64(run_seed);
+        let result = ga::run(&mut rng, ga_specs, data_splits)?;
+        write_stats(stats_writer, run_index, &result)?;
+        records.push(RunRecord {
+            seed: run_seed,
+            best_fitness: result
+                .generations
+                .iter()
+                .map(|generation| generation.max_fitness)
+                .collect(),
+            optimum_generation: result.optimum_generation,
+        });
+    }
+    let summary = RunsSummary::new(records);
+
+    println!("summary:");
+    println!(
+        "{:>10} {:>10} {:>10} {:>10}",
+        "generation", "mean", "median", "stddev"
+    );
+    for generation in &summary.generations {
+        println!(
+            "{:>10} {:>10.4} {:>10.4} {:>10.4}",
+            generation.generation,
+            generation.best_fitness.mean,
+            generation.best_fitness.median,
+            generation.best_fitness.stddev
+        );
+    }
+    println!("runs.count={}", summary.runs.len());
+    println!("runs.successRate={:.4}", summary.success_rate);
+    let generations_to_optimum = summary.generations_to_optimum;
+    println!(
+        "runs.generationsToOptimum.mean={}",
+        format_ratio(generations_to_optimum.map(|statistics| statistics.mean))
+    );
+    println!(
+        "runs.generationsToOptimum.median={}",
+        format_ratio(generations_to_optimum.map(|statistics| statistics.median))
+    );
+    println!(
+        "runs.generationsToOptimum.stddev={}",
+        format_ratio(generations_to_optimum.map(|statistics| statistics.stddev))
+    );
+
+    if let Some(output) = output {
+        let file = File::create(output)?;
+        serde_json::to_writer_pretty(BufWriter::new(file), &summary)?;
+    }
+    Ok(())
+}
+
+/// Runs the GA once per fold, training on the rest of the folds and testing on the fold itself
+fn cross_validate(
+    rng: &mut Pcg64,
+    ga_specs_input: &GaSpecInput,
+    data_set: &DataSet,
+    classes: &[String],
+    fold_count: usize,
+    stats_writer: &mut Option<StatsFile>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let folds = data_set.stratified_folds(rng, fold_count)?;
+
+    let mut results: Vec<(Champion, DataSplits)> = Vec::with_capacity(folds.len());
+    for (fold_index, test) in folds.iter().enumerate() {
+        let training_folds: Vec<&DataSet> = folds
+            .iter()
+            .enumerate()
+            .filter(|(index, _)| *index != fold_index)
+            .map(|(_, fold)| fold)
+            .collect();
+        let data_splits = DataSplits {
+            training: DataSet::concat(&training_folds),
+            validation: None,
+            test: test.clone(),
+        };
+
+        println!("fold: {}", fold_index);
+        let ga_specs = build_specs(ga_specs_input, classes, &data_splits)?;
+        let result = ga::run(rng, &ga_specs, &data_splits)?;
+        write_stats(stats_writer, fold_index, &result)?;
+        results.push((result.champion, data_splits));
+    }
+
+    println!("summary:");
+    let mut training_accuracies = Vec::with_capacity(results.len());
+    let mut test_accuracies = Vec::with_capacity(results.len());
+    for (fold_index, (champion, data_splits)) in results.iter().enumerate() {
+        print_champion(
+            &format!("fold[{}]", fold_index),
+            champion,
+            data_splits,
+            classes,
+        )?;
+        training_accuracies.extend(champion.candidate.accuracy(&data_splits.training)?);
+        test_accuracies.extend(champion.candidate.accuracy(&data_splits.test)?);
+    }
+    for (name, accuracies) in &[
+        ("training", &training_accuracies),
+        ("test", &test_accuracies),
+    ] {
+        println!(
+            "folds.{}Accuracy.mean={}",
+            name,
+            format_ratio(metrics::mean(accuracies))
+        );
+        println!(
+            "folds.{}Accuracy.stddev={}",
+            name,
+            format_ratio(metrics::standard_deviation(accuracies))
+        );
+    }
+    Ok(())
+}
 
-            total += fitness;
+/// Writes out the statistics of every generation of a run, if a stats file was asked for
+fn write_stats(
+    stats_writer: &mut Option<StatsFile>,
+    run: usize,
+    result: &RunResult,
+) -> Result<(), StatsError> {
+    if let Some(stats_writer) = stats_writer {
+        for generation in &result.generations {
+            stats_writer.write(run, generation)?;
         }
+        stats_writer.flush()?;
+    }
+    Ok(())
+}
 
-        let average: f64 = total as f64 / fitness.len() as f64;
-        println!("generation: {}", population.generation());
-        println!("population.size={}", population.len());
-        println!("population.averageFitness={}", average);
-        println!("population.maxFitness={}", max.unwrap());
-        println!("population.minFitness={}", min.unwrap());
-
-        let selection = ga_specs.selection.select(&fitness)?;
-        println!("{} candidates selected for crossover", selection.len());
-        let offsprings = ga_specs.crossover.crossover(&selection)?;
-        println!("{} new offsprings", offsprings.len());
-        population.append(offsprings);
-        ga_specs.mutation.mutate(&mut population, &ga_specs)?;
-
-        if max.unwrap() == training.len() && ga_specs.stop_at_optimum_fitness {
-            break;
+/// Completes the spec with what is calculated from the training data and prints it out
+fn build_specs(
+    ga_specs_input: &GaSpecInput,
+    classes: &[String],
+    data_splits: &DataSplits,
+) -> Result<GaSpec, RunError> {
+    let calculated = CalculatedSpecs::from_training(&data_splits.training, classes.to_vec())
+        .ok_or(RunError::EmptyTrainingSet)?;
+    let ga_specs: GaSpec = (ga_specs_input.clone(), calculated).into();
+
+    println!("{:#?}", ga_specs);
+    for (name, data_set) in data_splits.named() {
+        println!("{} data set size: {}", name, data_set.len());
+    }
+    Ok(ga_specs)
+}
+
+/// Prints how well the champion does on each of the data sets, along with its rules
+fn print_champion(
+    prefix: &str,
+    champion: &Champion,
+    data_splits: &DataSplits,
+    classes: &[String],
+) -> Result<(), Box<dyn std::error::Error>> {
+    let candidate = &champion.candidate;
+    println!("{}.generation={}", prefix, champion.generation);
+    println!("{}.trainingFitness={}", prefix, champion.fitness);
+    for (name, data_set) in data_splits.named() {
+        println!(
+            "{}.{}Accuracy={}",
+            prefix,
+            name,
+            format_ratio(candidate.accuracy(data_set)?)
+        );
+    }
+    for (name, data_set) in data_splits.named() {
+        for class_metrics in metrics::class_metrics(candidate, data_set, classes)? {
+            println!(
+                "{}.{}.class[{}]: support={} precision={} recall={}",
+                prefix,
+                name,
+                class_metrics.class,
+                class_metrics.support,
+                format_ratio(class_metrics.precision()),
+                format_ratio(class_metrics.recall()),
+            );
         }
-        population.increment_generation();
     }
+    println!("{}.rules={:?}", prefix, candidate.rules());
+    println!("{}.defaultOutput={}", prefix, candidate.default_output());
     Ok(())
 }
@@ -0,0 +1,367 @@
+use crate::candidate::{Candidate, FitnessCalculationError};
+use crate::dataset::DataSet;
+use serde::ser::SerializeStruct;
+use serde::{Serialize, Serializer};
+
+/// How well a candidate does on a single class
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClassMetrics {
+    pub class: String,
+    /// The number of items that belong to the class
+    pub support: usize,
+    /// The number of items that were predicted to belong to the class
+    pub predicted: usize,
+    /// The number of items that belong to the class and were predicted as such
+    pub true_positives: usize,
+}
+
+impl ClassMetrics {
+    /// Returns none if nothing was predicted to belong to the class
+    pub fn precision(&self) -> Option<f64> {
+        if self.predicted == 0 {
+            return None;
+        }
+        Some(self.true_positives as f64 / self.predicted as f64)
+    }
+
+    /// Returns none if nothing belongs to the class
+    pub fn recall(&self) -> Option<f64> {
+        if self.support == 0 {
+            return None;
+        }
+        Some(self.true_positives as f64 / self.support as f64)
+    }
+
+    /// The harmonic mean of the precision and the recall. Returns none if either of them is
+    /// missing
+    pub fn f1(&self) -> Option<f64> {
+        let precision = self.precision()?;
+        let recall = self.recall()?;
+        if precision + recall == 0.0 {
+            return Some(0.0);
+        }
+        Some(2.0 * precision * recall / (precision + recall))
+    }
+}
+
+// Serialized along with the ratios so that the output can be read without recalculating them
+impl Serialize for ClassMetrics {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("ClassMetrics", 7)?;
+        state.serialize_field("class", &self.class)?;
+        state.serialize_field("support", &self.support)?;
+        state.serialize_field("predicted", &self.predicted)?;
+        state.serialize_field("true_positives", &self.true_positives)?;
+        state.serialize_field("precision", &self.precision())?;
+        state.serialize_field("recall", &self.recall())?;
+        state.serialize_field("f1", &self.f1())?;
+        state.end()
+    }
+}
+
+/// How a single rule of a decision list does
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RuleMetrics {
+    /// The position of the rule within the candidate. None for the default output
+    pub index: Option<usize>,
+    pub output: String,
+    /// The number of items the rule decided the output of
+    pub fired: usize,
+    /// The number of items the rule decided the output of correctly
+    pub correct: usize,
+    /// The fraction of the data set the rule decided the output of
+    pub coverage: Option<f64>,
+    /// The fraction of the items the rule decided the output of that were correct
+    pub precision: Option<f64>,
+}
+
+/// A full report of how a candidate does on a labeled data set
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Evaluation {
+    /// The classes of the data set and the outputs of the candidate, sorted
+    pub classes: Vec<String>,
+    /// Rows are the actual classes and columns are the predicted classes, in the order of
+    /// `classes`
+    pub confusion_matrix: Vec<Vec<usize>>,
+    pub size: usize,
+    pub accuracy: Option<f64>,
+    /// The mean recall of the classes that occur in the data set
+    pub balanced_accuracy: Option<f64>,
+    /// Precision, recall and F1 are averaged over the classes that occur in the data set. A
+    /// missing value counts as 0
+    pub macro_precision: Option<f64>,
+    pub macro_recall: Option<f64>,
+    pub macro_f1: Option<f64>,
+    /// The Matthews correlation coefficient, generalized to any number of classes
+    pub mcc: Option<f64>,
+    pub class_metrics: Vec<ClassMetrics>,
+    /// The rules in order, followed by the default output
+    pub rule_metrics: Vec<RuleMetrics>,
+}
+
+impl Candidate {
+    /// Evaluates the candidate on a labeled data set
+    pub fn evaluate(
+        &self,
+        data_set: &DataSet,
+        classes: &[String],
+    ) -> Result<Evaluation, FitnessCalculationError> {
+        let mut all_classes: Vec<String> = classes.to_vec();
+        all_classes.extend(data_set.classes());
+        all_classes.extend(self.rules().iter().map(|rule| rule.output().to_owned()));
+        all_classes.push(self.default_output().to_owned());
+        all_classes.sort();
+        all_classes.dedup();
+
+        let class_index = |class: &str| {
+            all_classes
+                .binary_search_by(|existing| existing.as_str().cmp(class))
+                .expect("every class is known")
+        };
+
+        let mut confusion_matrix = vec![vec![0; all_classes.len()]; all_classes.len()];
+        // The last entry is the default output
+        let mut fired = vec![0; self.rules().len() + 1];
+        let mut correct = vec![0; self.rules().len() + 1];
+        for data_item in data_set.as_ref() {
+            let (rule_index, prediction) = self.classify(data_item.input())?;
+            let rule_index = rule_index.unwrap_or(self.rules().len());
+            fired[rule_index] += 1;
+            if prediction == data_item.output() {
+                correct[rule_index] += 1;
+            }
+            confusion_matrix[class_index(data_item.output())][class_index(prediction)] += 1;
+        }
+
+        let size = data_set.len();
+        let class_metrics: Vec<ClassMetrics> = all_classes
+            .iter()
+            .enumerate()
+            .map(|(index, class)| ClassMetrics {
+                class: class.clone(),
+                support: confusion_matrix[index].iter().sum(),
+                predicted: confusion_matrix.iter().map(|row| row[index]).sum(),
+                true_positives: confusion_matrix[index][index],
+            })
+            .collect();
+
+        let rule_metrics = fired
+            .iter()
+            .zip(&correct)
+            .enumerate()
+            .map(|(index, (fired, correct))| {
+                let rule = self.rules().get(index);
+                RuleMetrics {
+                    index: rule.map(|_| index),
+                    output: rule
+                        .map(|rule| rule.output())
+                        .unwrap_or_else(|| self.default_output())
+                        .to_owned(),
+                    fired: *fired,
+                    correct: *correct,
+                    coverage: ratio(*fired, size),
+                    precision: ratio(*correct, *fired),
+                }
+            })
+            .collect();
+
+        let true_positives: usize = class_metrics
+            .iter()
+            .map(|class_metrics| class_metrics.true_positives)
+            .sum();
+
+        Ok(Evaluation {
+            accuracy: ratio(true_positives, size),
+            balanced_accuracy: macro_average(&class_metrics, ClassMetrics::recall),
+            macro_precision: macro_average(&class_metrics, ClassMetrics::precision),
+            macro_recall: macro_average(&class_metrics, ClassMetrics::recall),
+            macro_f1: macro_average(&class_metrics, ClassMetrics::f1),
+            mcc: matthews_correlation(&class_metrics, true_positives, size),
+            classes: all_classes,
+            confusion_matrix,
+            size,
+            class_metrics,
+            rule_metrics,
+        })
+    }
+}
+
+fn ratio(numerator: usize, denominator: usize) -> Option<f64> {
+    if denominator == 0 {
+        return None;
+    }
+    Some(numerator as f64 / denominator as f64)
+}
+
+/// Averages a metric over the classes that occur in the data set. A missing value counts as 0.
+/// Returns none if no class occurs
+pub(crate) fn macro_average(
+    class_metrics: &[ClassMetrics],
+    metric: fn(&ClassMetrics) -> Option<f64>,
+) -> Option<f64> {
+    let values: Vec<f64> = class_metrics
+        .iter()
+        .filter(|class_metrics| class_metrics.support > 0)
+        .map(|class_metrics| metric(class_metrics).unwrap_or_default())
+        .collect();
+    mean(&values)
+}
+
+/// Gorodkin's generalization of the Matthews correlation coefficient to any number of classes.
+/// Returns none if every item belongs to, or is predicted as, a single class
+pub(crate) fn matthews_correlation(
+    class_metrics: &[ClassMetrics],
+    correct: usize,
+    size: usize,
+) -> Option<f64> {
+    let correct = correct as f64;
+    let size = size as f64;
+    let mut predicted_times_actual = 0.0;
+    let mut predicted_squared = 0.0;
+    let mut actual_squared = 0.0;
+    for class_metrics in class_metrics {
+        let predicted = class_metrics.predicted as f64;
+        let actual = class_metrics.support as f64;
+        predicted_times_actual += predicted * actual;
+        predicted_squared += predicted * predicted;
+        actual_squared += actual * actual;
+    }
+    let denominator = ((size * size - predicted_squared) * (size * size - actual_squared)).sqrt();
+    if denominator == 0.0 {
+        return None;
+    }
+    Some((correct * size - predicted_times_actual) / denominator)
+}
+
+/// Calculates the metrics of a candidate for each of the classes
+pub fn class_metrics(
+    candidate: &Candidate,
+    data_set: &DataSet,
+    classes: &[String],
+) -> Result<Vec<ClassMetrics>, FitnessCalculationError> {
+    let mut metrics: Vec<ClassMetrics> = classes
+        .iter()
+        .map(|class| ClassMetrics {
+            class: class.clone(),
+            support: 0,
+            predicted: 0,
+            true_positives: 0,
+        })
+        .collect();
+
+    for data_item in data_set.as_ref() {
+        let prediction = candidate.predict(data_item.input())?;
+        for class_metrics in metrics.iter_mut() {
+            let is_actual = class_metrics.class == data_item.output();
+            let is_predicted = class_metrics.class == prediction;
+            if is_actual {
+                class_metrics.support += 1;
+            }
+            if is_predicted {
+                class_metrics.predicted += 1;
+            }
+            if is_actual && is_predicted {
+                class_metrics.true_positives += 1;
+            }
+        }
+    }
+    Ok(metrics)
+}
+
+/// The arithmetic mean. Returns none if there are no values
+pub fn mean(values: &[f64]) -> Option<f64> {
+    if values.is_empty() {
+        return None;
+    }
+    Some(values.iter().sum::<f64>() / values.len() as f64)
+}
+
+/// The population standard deviation. Returns none if there are no values
+pub fn standard_deviation(values: &[f64]) -> Option<f64> {
+    let mean = mean(values)?;
+    let variance = values
+        .iter()
+        .map(|value| (value - mean).powi(2))
+        .sum::<f64>()
+        / values.len() as f64;
+    Some(variance.sqrt())
+}
+
+/// The middle value, or the mean of the two middle values. Returns none if there are no values
+pub fn median(values: &[f64]) -> Option<f64> {
+    if values.is_empty() {
+        return None;
+    }
+    let mut sorted = values.to_vec();
+    sorted.sort_by(|a, b| a.partial_cmp(b).expect("values are not comparable"));
+    let middle = sorted.len() / 2;
+    if sorted.len() % 2 == 1 {
+        Some(sorted[middle])
+    } else {
+        Some((sorted[middle - 1] + sorted[middle]) / 2.0)
+    }
+}
+
+/// A summary of a list of values
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct Statistics {
+    pub mean: f64,
+    pub median: f64,
+    pub stddev: f64,
+}
+
+impl Statistics {
+    /// Returns none if there are no values
+    pub fn from_values(values: &[f64]) -> Option<Statistics> {
+        Some(Statistics {
+            mean: mean(values)?,
+            median: median(values)?,
+            stddev: standard_deviation(values)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_evaluate() {
+        use crate::rule::{Constraint, Rule};
+        use std::collections::BTreeMap;
+
+        let mut constraints = BTreeMap::new();
+        constraints.insert(0, Constraint::Character('1'));
+        let candidate =
+            Candidate::from_rules(vec![Rule::new(constraints, "1".to_owned())], "0".to_owned());
+        let mut data_set = DataSet::new();
+        for line in &["1 1", "1 0", "0 0", "0 0"] {
+            data_set.push(line.parse().unwrap()).unwrap();
+        }
+
+        let evaluation = candidate.evaluate(&data_set, &[]).unwrap();
+        assert_eq!(evaluation.classes, vec!["0", "1"]);
+        assert_eq!(evaluation.confusion_matrix, vec![vec![2, 1], vec![0, 1]]);
+        assert_eq!(evaluation.accuracy, Some(0.75));
+        assert_eq!(evaluation.balanced_accuracy, Some((2.0 / 3.0 + 1.0) / 2.0));
+        assert_eq!(evaluation.mcc, Some(4.0 / 48f64.sqrt()));
+        assert_eq!(evaluation.rule_metrics[0].fired, 2);
+        assert_eq!(evaluation.rule_metrics[0].precision, Some(0.5));
+        assert_eq!(evaluation.rule_metrics[1].index, None);
+        assert_eq!(evaluation.rule_metrics[1].correct, 2);
+    }
+
+    #[test]
+    fn test_statistics() {
+        assert_eq!(Statistics::from_values(&[]), None);
+        assert_eq!(
+            Statistics::from_values(&[4.0, 1.0, 3.0, 2.0]),
+            Some(Statistics {
+                mean: 2.5,
+                median: 2.5,
+                stddev: 1.25f64.sqrt(),
+            })
+        );
+        assert_eq!(median(&[3.0, 1.0, 2.0]), Some(2.0));
+    }
+}
@@ -0,0 +1,159 @@
+use crate::candidate::Candidate;
+use crate::candidate::FitnessCalculationError;
+use crate::ga::{Champion, DataSplits};
+use crate::ga_spec::{GaSpec, RepresentationSpec};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::BufWriter;
+use std::path::Path;
+use thiserror::Error;
+
+/// The version of the model format written by this build. Bumped whenever the format changes in
+/// a way older readers cannot handle
+pub const MODEL_VERSION: u32 = 1;
+
+/// How well the model did on one of the data sets it was built with
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DataSetMetrics {
+    pub size: usize,
+    pub accuracy: Option<f64>,
+}
+
+/// A trained rule set along with what is needed to use and reproduce it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Model {
+    pub version: u32,
+    /// The rules, each with the class it predicts, and the default class
+    pub candidate: Candidate,
+    /// The number of features an input is expected to have
+    pub feature_width: usize,
+    pub representation: RepresentationSpec,
+    pub alphabet: String,
+    pub classes: Vec<String>,
+    /// Sha256 of the spec file the model was trained with
+    pub spec_hash: String,
+    pub seed: Option<u64>,
+    /// Keyed by the name of the data set: training, validation or test
+    pub metrics: BTreeMap<String, DataSetMetrics>,
+}
+
+#[derive(Error, Debug)]
+pub enum ModelError {
+    #[error("an io error occured")]
+    IoError(std::io::ErrorKind),
+
+    #[error("json error: {0}")]
+    JsonError(#[from] serde_json::Error),
+
+    #[error(
+        "model version {0} is not supported, expected version {}",
+        MODEL_VERSION
+    )]
+    UnsupportedVersion(u32),
+
+    #[error(transparent)]
+    FitnessCalculationError(#[from] FitnessCalculationError),
+}
+
+/// Hashes the contents of a spec file so that a model can be traced back to its spec
+pub fn spec_hash(contents: &[u8]) -> String {
+    Sha256::digest(contents)
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+impl Model {
+    pub fn new(
+        champion: &Champion,
+        ga_spec: &GaSpec,
+        spec_hash: String,
+        data_splits: &DataSplits,
+    ) -> Result<Model, ModelError> {
+        let mut metrics = BTreeMap::new();
+        for (name, data_set) in data_splits.named() {
+            metrics.insert(
+                name.to_owned(),
+                DataSetMetrics {
+                    size: data_set.len(),
+                    accuracy: champion.candidate.accuracy(data_set)?,
+                },
+            );
+        }
+        Ok(Model {
+            version: MODEL_VERSION,
+            candidate: champion.candidate.clone(),
+            feature_width: ga_spec.calculated.max_index,
+            representation: ga_spec.representation,
+            alphabet: ga_spec.calculated.alphabet.to_owned(),
+            classes: ga_spec.calculated.classes.clone(),
+            spec_hash,
+            seed: ga_spec.seed,
+            metrics,
+        })
+    }
+
+    /// Reads a model, refusing models written in any version of the format other than
+    /// `MODEL_VERSION`
+    pub fn from_file<T: AsRef<Path>>(path: T) -> Result<Model, ModelError> {
+        let contents =
+            fs::read_to_string(path.as_ref()).map_err(|err| ModelError::IoError(err.kind()))?;
+        Model::from_json(&contents)
+    }
+
+    pub fn from_json(contents: &str) -> Result<Model, ModelError> {
+        #[derive(Deserialize)]
+        struct Version {
+            version: u32,
+        }
+        // Check the version first so that a model of another version, older or newer, fails with a
+        // clear error rather than a parse error
+        let Version { version } = serde_json::from_str(contents)?;
+        if version != MODEL_VERSION {
+            return Err(ModelError::UnsupportedVersion(version));
+        }
+        Ok(serde_json::from_str(contents)?)
+    }
+
+    pub fn to_file<T: AsRef<Path>>(&self, path: T) -> Result<(), ModelError> {
+        let file = File::create(path.as_ref()).map_err(|err| ModelError::IoError(err.kind()))?;
+        serde_json::to_writer_pretty(BufWriter::new(file), self)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::rule::Rule;
+
+    #[test]
+    fn test_round_trip() {
+        let model = Model {
+            version: MODEL_VERSION,
+            candidate: Candidate::from_rules(
+                vec![Rule::new(BTreeMap::new(), "1".to_owned())],
+                "0".to_owned(),
+            ),
+            feature_width: 5,
+            representation: RepresentationSpec::Binary,
+            alphabet: "01".to_owned(),
+            classes: vec!["0".to_owned(), "1".to_owned()],
+            spec_hash: spec_hash(b"{}"),
+            seed: Some(42),
+            metrics: BTreeMap::new(),
+        };
+        let json = serde_json::to_string(&model).unwrap();
+        assert_eq!(Model::from_json(&json).unwrap(), model);
+        assert!(matches!(
+            Model::from_json(&json.replace("\"version\":1", "\"version\":2")),
+            Err(ModelError::UnsupportedVersion(2))
+        ));
+        assert!(matches!(
+            Model::from_json(&json.replace("\"version\":1", "\"version\":0")),
+            Err(ModelError::UnsupportedVersion(0))
+        ));
+    }
+}
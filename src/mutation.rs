@@ -1,12 +1,12 @@
 use crate::candidate::Candidate;
 use crate::ga_spec::GaSpec;
 use crate::population::Population;
-use crate::rule::Rule;
-use rand::{self, Rng};
-use serde::Deserialize;
+use crate::rule::{generate_output, Constraint, Rule};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct MutationStrategy {
     #[serde(flatten)]
     pub options: MutationStrategyCommonOptions,
@@ -14,21 +14,39 @@ pub struct MutationStrategy {
     pub variant: MutationStrategyVariant,
 }
 
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 #[serde(tag = "type")]
 #[serde(rename_all = "snake_case")]
 pub enum MutationStrategyVariant {
-    ConstraintSwap { delta: isize },
-    ConstraintRandomize { swap_if_fail: bool, retries: usize },
+    ConstraintSwap {
+        delta: isize,
+    },
+    ConstraintRandomize {
+        swap_if_fail: bool,
+        retries: usize,
+    },
     ConstraintValueRandomize,
+    /// Moves both bounds of an interval constraint by up to `delta`(a fraction of the range of the
+    /// feature)
+    IntervalCreep {
+        delta: f64,
+    },
+    /// Moves each bound of an interval constraint outwards or inwards by up to `delta`(a fraction
+    /// of the range of the feature)
+    IntervalWiden {
+        delta: f64,
+    },
 }
 
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct MutationStrategyCommonOptions {
     pub chance: Option<usize>,
     pub chance_per_candidate: Option<usize>,
     pub chance_per_rule: Option<usize>,
     pub chance_per_constraint: Option<usize>,
+    /// The chance of the output of a mutated rule, or the default output of a mutated candidate,
+    /// being randomized
+    pub chance_per_output: Option<usize>,
 }
 
 #[derive(Error, Debug)]
@@ -38,30 +56,37 @@ pub enum MutationError {
 }
 
 impl MutationStrategy {
-    pub fn mutate(
+    /// Mutates the candidates of the population in place. Elites are left untouched. Returns the
+    /// number of candidates that were mutated
+    pub fn mutate<T: Rng>(
         &self,
-        mut population: &mut Population,
+        mut rng: &mut T,
+        population: &mut Population,
+        elites: &[Candidate],
         ga_spec: &GaSpec,
-    ) -> Result<(), MutationError> {
-        let mut rng = rand::thread_rng();
+    ) -> Result<usize, MutationError> {
         // Rng makes  it very easy to generate a boolean based on a probablity
         let chance = self.options.chance.unwrap_or_default();
 
         // Early return if chance is 0
         if chance == 0 {
-            return Ok(());
+            return Ok(0);
         }
 
         // If the rng gods tell us not to mutate, we wont mutate
         // TODO: Add a proper percentage type
         if !rng.gen_ratio(chance as u32, 100) {
-            return Ok(());
+            return Ok(0);
         }
 
         let candidates = population.candidates();
         let mut changes: Vec<(Candidate, Candidate)> = Vec::new();
 
         for orig_candidate in candidates {
+            if elites.contains(orig_candidate) {
+                continue;
+            }
+
             if !rng.gen_ratio(
                 self.options.chance_per_candidate.unwrap_or_default() as u32,
                 100,
@@ -71,14 +96,27 @@ impl MutationStrategy {
 
             let candidate = orig_candidate.clone();
 
-            let mut rules: Vec<Rule> = candidate.rules().iter().map(|rule| rule.clone()).collect();
+            let mut rules: Vec<Rule> = candidate.rules().to_vec();
+            let mut default_output = candidate.default_output().to_owned();
             let mut ran = false;
 
+            let chance_per_output = self.options.chance_per_output.unwrap_or_default() as u32;
+
+            if rng.gen_ratio(chance_per_output, 100) {
+                ran = true;
+                default_output = generate_output(&mut rng, ga_spec);
+            }
+
             for rule in rules.iter_mut() {
                 if !rng.gen_ratio(self.options.chance_per_rule.unwrap_or_default() as u32, 100) {
                     continue;
                 }
 
+                if rng.gen_ratio(chance_per_output, 100) {
+                    ran = true;
+                    rule.set_output(generate_output(&mut rng, ga_spec));
+                }
+
                 for constraint_key in 0..ga_spec.calculated.max_index {
                     if !rng.gen_ratio(
                         self.options.chance_per_constraint.unwrap_or_default() as u32,
@@ -149,46 +187,63 @@ impl MutationStrategy {
                             }
                         }
                         MutationStrategyVariant::ConstraintValueRandomize => {
-                            let change = if let Some(character) =
-                                rule.constraints().get(&constraint_key)
-                            {
+                            let change = if rule.constraints().contains_key(&constraint_key) {
                                 // Lets calculate the chance of the existing constraint being
                                 // removed
                                 // The chance is 1 in alphabet+1
                                 if rng.gen_ratio(1, ga_spec.calculated.max_index as u32 + 1) {
                                     None
                                 } else {
-                                    let pos = rng
-                                        .gen_range(0, ga_spec.calculated.alphabet.len() as u32)
-                                        as usize;
-                                    let new_char =
-                                        ga_spec.calculated.alphabet.chars().nth(pos).unwrap();
-                                    Some(if new_char == *character {
-                                        ga_spec.calculated.alphabet.chars().nth(pos).unwrap()
-                                    } else {
-                                        new_char
-                                    })
+                                    Some(Constraint::generate(&mut rng, ga_spec, constraint_key))
                                 }
                             } else {
-                                let pos = rng.gen_range(0, ga_spec.calculated.alphabet.len() as u32)
-                                    as usize;
-                                let new_char =
-                                    ga_spec.calculated.alphabet.chars().nth(pos).unwrap();
-                                Some(new_char)
+                                Some(Constraint::generate(&mut rng, ga_spec, constraint_key))
                             };
 
-                            if let Some(character) = change {
-                                rule.constraints_mut().insert(constraint_key, character);
+                            if let Some(constraint) = change {
+                                rule.constraints_mut().insert(constraint_key, constraint);
                             } else {
                                 rule.constraints_mut().remove(&constraint_key);
                             }
                         }
+                        MutationStrategyVariant::IntervalCreep { delta } => {
+                            let (min, max) = ga_spec.calculated.value_ranges[constraint_key];
+                            let delta = delta * (max - min);
+                            if let Some(constraint) =
+                                rule.constraints_mut().get_mut(&constraint_key)
+                            {
+                                if delta > 0.0 {
+                                    let offset = rng.gen_range(-delta, delta);
+                                    constraint.creep(offset, (min, max));
+                                }
+                            }
+                        }
+                        MutationStrategyVariant::IntervalWiden { delta } => {
+                            let (min, max) = ga_spec.calculated.value_ranges[constraint_key];
+                            let delta = delta * (max - min);
+                            if let Some(constraint) =
+                                rule.constraints_mut().get_mut(&constraint_key)
+                            {
+                                if delta > 0.0 {
+                                    let lower_delta = rng.gen_range(-delta, delta);
+                                    let upper_delta = rng.gen_range(-delta, delta);
+                                    constraint.widen(lower_delta, upper_delta, (min, max));
+                                }
+                            }
+                        }
                     }
                 }
             }
 
             if ran {
-                let new_candidate = Candidate::from_rules(&rules.into_iter().collect());
+                let mut new_candidate = Candidate::from_rules(rules, default_output);
+                if ga_spec.unique_rules {
+                    new_candidate.dedup_rules();
+                }
+                if let Some(birth_generation_id) = candidate.birth_generation_id() {
+                    new_candidate.set_birth_generation_id(birth_generation_id);
+                }
+                new_candidate.set_mutation_count(candidate.mutation_count() + 1);
                 if new_candidate != candidate
                     && !population.contains(&new_candidate)
                     && changes
@@ -203,11 +258,12 @@ impl MutationStrategy {
             }
         }
 
+        let mutated = changes.len();
         for (remove_me, add_me) in changes.into_iter() {
             population.remove(&remove_me);
             population.insert(add_me);
         }
 
-        Ok(())
+        Ok(mutated)
     }
 }
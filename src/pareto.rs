@@ -0,0 +1,247 @@
+use crate::candidate::{Candidate, CandidateFitness};
+use crate::fitness::Fitness;
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+
+/// A quantity candidates are compared on when optimising for more than one objective
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Objective {
+    /// The fitness calculated with the fitness section of the spec. Maximised
+    Fitness,
+    /// The number of rules. Minimised
+    Rules,
+    /// The number of constraints across all of the rules. Minimised
+    Constraints,
+}
+
+impl Objective {
+    pub fn value(&self, candidate_fitness: &CandidateFitness) -> f64 {
+        match self {
+            Objective::Fitness => candidate_fitness.fitness.value(),
+            Objective::Rules => candidate_fitness.candidate.rules().len() as f64,
+            Objective::Constraints => candidate_fitness.candidate.constraint_count() as f64,
+        }
+    }
+
+    pub fn is_maximised(&self) -> bool {
+        matches!(self, Objective::Fitness)
+    }
+}
+
+/// Accuracy against the size of the rule set
+pub fn default_objectives() -> Vec<Objective> {
+    vec![Objective::Fitness, Objective::Rules, Objective::Constraints]
+}
+
+/// Where a candidate stands against the rest of the candidates it was ranked with
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ParetoRank {
+    /// The non-dominated front the candidate is in, starting from 0 for the candidates no other
+    /// candidate dominates
+    pub front: usize,
+    /// How far the candidate is from its neighbours on the same front. The candidates at the
+    /// ends of a front have an infinite distance
+    pub crowding_distance: f64,
+}
+
+impl ParetoRank {
+    /// The crowded comparison of NSGA-II: the lower front comes first, and within a front the
+    /// more isolated candidate comes first
+    pub fn crowded_cmp(&self, other: &ParetoRank) -> Ordering {
+        self.front
+            .cmp(&other.front)
+            .then_with(|| other.crowding_distance.total_cmp(&self.crowding_distance))
+    }
+}
+
+/// A candidate on the Pareto front of the final population
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ParetoMember {
+    pub candidate: Candidate,
+    pub fitness: Fitness,
+    pub rules: usize,
+    pub constraints: usize,
+}
+
+impl From<&CandidateFitness<'_>> for ParetoMember {
+    fn from(candidate_fitness: &CandidateFitness<'_>) -> Self {
+        ParetoMember {
+            candidate: candidate_fitness.candidate.clone(),
+            fitness: candidate_fitness.fitness,
+            rules: candidate_fitness.candidate.rules().len(),
+            constraints: candidate_fitness.candidate.constraint_count(),
+        }
+    }
+}
+
+/// The objective values of each candidate, negated where needed so that greater is always better
+fn oriented_values(candidates: &[CandidateFitness], objectives: &[Objective]) -> Vec<Vec<f64>> {
+    candidates
+        .iter()
+        .map(|candidate_fitness| {
+            objectives
+                .iter()
+                .map(|objective| {
+                    let value = objective.value(candidate_fitness);
+                    if objective.is_maximised() {
+                        value
+                    } else {
+                        -value
+                    }
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// Whether `a` is at least as good as `b` on every objective and better on at least one
+fn dominates(a: &[f64], b: &[f64]) -> bool {
+    a.iter().zip(b).all(|(a, b)| a >= b) && a.iter().zip(b).any(|(a, b)| a > b)
+}
+
+/// Splits the candidates into non-dominated fronts, from the best to the worst
+fn non_dominated_fronts(values: &[Vec<f64>]) -> Vec<Vec<usize>> {
+    let mut dominated_by: Vec<Vec<usize>> = vec![vec![]; values.len()];
+    let mut domination_count = vec![0; values.len()];
+    for (first, first_values) in values.iter().enumerate() {
+        for (second, second_values) in values.iter().enumerate().skip(first + 1) {
+            if dominates(first_values, second_values) {
+                dominated_by[first].push(second);
+                domination_count[second] += 1;
+            } else if dominates(second_values, first_values) {
+                dominated_by[second].push(first);
+                domination_count[first] += 1;
+            }
+        }
+    }
+
+    let mut fronts = vec![];
+    let mut front: Vec<usize> = (0..values.len())
+        .filter(|index| domination_count[*index] == 0)
+        .collect();
+    while !front.is_empty() {
+        let mut next_front = vec![];
+        for index in &front {
+            for dominated in &dominated_by[*index] {
+                domination_count[*dominated] -= 1;
+                if domination_count[*dominated] == 0 {
+                    next_front.push(*dominated);
+                }
+            }
+        }
+        next_front.sort_unstable();
+        fronts.push(front);
+        front = next_front;
+    }
+    fronts
+}
+
+/// Adds the distance between the neighbours of each candidate on the front along one objective,
+/// normalised by the range of the objective on the front
+fn add_crowding_distances(
+    front: &[usize],
+    values: &[Vec<f64>],
+    objective: usize,
+    ranks: &mut [ParetoRank],
+) {
+    let mut sorted = front.to_vec();
+    sorted.sort_by(|a, b| values[*a][objective].total_cmp(&values[*b][objective]));
+    let (first, last) = match (sorted.first(), sorted.last()) {
+        (Some(first), Some(last)) => (*first, *last),
+        _ => return,
+    };
+    ranks[first].crowding_distance = f64::INFINITY;
+    ranks[last].crowding_distance = f64::INFINITY;
+    let range = values[last][objective] - values[first][objective];
+    if range == 0.0 {
+        return;
+    }
+    for window in sorted.windows(3) {
+        ranks[window[1]].crowding_distance +=
+            (values[window[2]][objective] - values[window[0]][objective]) / range;
+    }
+}
+
+/// Ranks the candidates by non-dominated sorting, and by crowding distance within each front
+pub fn rank(candidates: &[CandidateFitness], objectives: &[Objective]) -> Vec<ParetoRank> {
+    let values = oriented_values(candidates, objectives);
+    let mut ranks = vec![
+        ParetoRank {
+            front: 0,
+            crowding_distance: 0.0,
+        };
+        candidates.len()
+    ];
+    for (front_index, front) in non_dominated_fronts(&values).iter().enumerate() {
+        for index in front {
+            ranks[*index].front = front_index;
+        }
+        for objective in 0..objectives.len() {
+            add_crowding_distances(front, &values, objective, &mut ranks);
+        }
+    }
+    ranks
+}
+
+/// The indices of the candidates from the best to the worst by crowded comparison, which is the
+/// order NSGA-II picks survivors in
+pub fn crowded_order(candidates: &[CandidateFitness], objectives: &[Objective]) -> Vec<usize> {
+    let ranks = rank(candidates, objectives);
+    let mut order: Vec<usize> = (0..candidates.len()).collect();
+    order.sort_by(|a, b| ranks[*a].crowded_cmp(&ranks[*b]));
+    order
+}
+
+/// The candidates no other candidate dominates
+pub fn front<'a>(
+    candidates: &[CandidateFitness<'a>],
+    objectives: &[Objective],
+) -> Vec<CandidateFitness<'a>> {
+    let values = oriented_values(candidates, objectives);
+    match non_dominated_fronts(&values).first() {
+        Some(front) => front.iter().map(|index| candidates[*index]).collect(),
+        None => vec![],
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::rule::Rule;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn test_rank() {
+        let candidate = |rules: usize| {
+            Candidate::from_rules(
+                vec![Rule::new(BTreeMap::new(), "1".to_owned()); rules],
+                "0".to_owned(),
+            )
+        };
+        let candidates: Vec<Candidate> = (1..=4).map(candidate).collect();
+        // Fitness and rule count of (1, 1), (3, 2), (2, 3) and (4, 4)
+        let candidates: Vec<CandidateFitness> = candidates
+            .iter()
+            .zip(&[1.0, 3.0, 2.0, 4.0])
+            .map(|(candidate, fitness)| CandidateFitness {
+                candidate,
+                fitness: Fitness::new(*fitness),
+            })
+            .collect();
+        let objectives = [Objective::Fitness, Objective::Rules];
+
+        let ranks = rank(&candidates, &objectives);
+        let fronts: Vec<usize> = ranks.iter().map(|rank| rank.front).collect();
+        assert_eq!(fronts, vec![0, 0, 1, 0]);
+        assert_eq!(ranks[0].crowding_distance, f64::INFINITY);
+        assert_eq!(ranks[1].crowding_distance, 2.0);
+        assert_eq!(crowded_order(&candidates, &objectives), vec![0, 3, 1, 2]);
+
+        let front: Vec<f64> = front(&candidates, &objectives)
+            .iter()
+            .map(|member| member.fitness.value())
+            .collect();
+        assert_eq!(front, vec![1.0, 3.0, 4.0]);
+    }
+}
@@ -1,16 +1,27 @@
 use crate::candidate::CandidateFitness;
 use crate::candidate::{Candidate, FitnessCalculationError};
-use crate::dataitem::DataItem;
 use crate::dataset::DataSet;
+use crate::fitness::{Fitness, FitnessSpec};
+use crate::fitness_cache::FitnessCache;
 use crate::ga_spec::GaSpec;
-use rand::{self, Rng};
+use crate::rule::Rule;
+use indexmap::IndexSet;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::DefaultHasher;
 use std::collections::HashSet;
+use std::hash::BuildHasherDefault;
+use std::thread;
+
+/// A set of candidates. Iterates in insertion order, so that the order is the same across runs and
+/// survives a checkpoint
+pub type CandidateSet = IndexSet<Candidate, BuildHasherDefault<DefaultHasher>>;
 
 /// A population is a collection of candidates
-#[derive(Debug, Clone, Eq)]
+#[derive(Debug, Clone, Default, Eq, Serialize, Deserialize)]
 pub struct Population {
     generation: usize,
-    candidates: HashSet<Candidate>,
+    candidates: CandidateSet,
 }
 
 impl PartialEq<Population> for Population {
@@ -32,11 +43,11 @@ impl Population {
         self.generation = new_generation;
     }
 
-    pub fn candidates(&self) -> &HashSet<Candidate> {
+    pub fn candidates(&self) -> &CandidateSet {
         &self.candidates
     }
 
-    pub fn candidates_mut(&mut self) -> &mut HashSet<Candidate> {
+    pub fn candidates_mut(&mut self) -> &mut CandidateSet {
         &mut self.candidates
     }
 
@@ -44,16 +55,21 @@ impl Population {
         self.candidates.len()
     }
 
+    pub fn is_empty(&self) -> bool {
+        self.candidates.is_empty()
+    }
+
     pub fn insert(&mut self, candidate: Candidate) -> bool {
         self.candidates.insert(candidate)
     }
 
     pub fn contains(&self, candidate: &Candidate) -> bool {
-        self.candidates.contains(&candidate)
+        self.candidates.contains(candidate)
     }
 
+    /// Removes a candidate, keeping the order of the rest
     pub fn remove(&mut self, candidate: &Candidate) -> bool {
-        self.candidates.remove(&candidate)
+        self.candidates.shift_remove(candidate)
     }
 
     pub fn append(&mut self, list: Vec<Candidate>) -> usize {
@@ -67,32 +83,56 @@ impl Population {
         added
     }
 
-    pub fn calculate_fitness<'a>(
+    /// The ratio of distinct rules to all the rules in the population. Returns none if there are
+    /// no rules
+    pub fn diversity(&self) -> Option<f64> {
+        let rules: Vec<&Rule> = self
+            .candidates
+            .iter()
+            .flat_map(|candidate| candidate.rules())
+            .collect();
+        if rules.is_empty() {
+            return None;
+        }
+        let distinct_rules: HashSet<&Rule> = rules.iter().copied().collect();
+        Some(distinct_rules.len() as f64 / rules.len() as f64)
+    }
+
+    /// Calculates the fitness of every candidate with the fitness function of the spec, sorted
+    /// from the least to the most fit. Only the candidates missing from the cache are evaluated,
+    /// divided between the threads of the spec, which gives the same result as evaluating them one
+    /// by one
+    pub fn calculate_fitness(
         &self,
         data_set: &'_ DataSet,
-    ) -> Result<Vec<CandidateFitness>, FitnessCalculationError> {
-        let mut fitness_values = Vec::with_capacity(self.candidates.len());
-        for candidate in &self.candidates {
-            fitness_values.push(CandidateFitness {
-                candidate,
-                fitness: candidate.calculate_fitness(&data_set)?,
-            });
-        }
+        spec: &GaSpec,
+        cache: &mut FitnessCache,
+    ) -> Result<Vec<CandidateFitness<'_>>, FitnessCalculationError> {
+        let candidates: Vec<&Candidate> = self.candidates.iter().collect();
+        let fitness = calculate_cached(&candidates, data_set, spec, cache)?;
+        let mut fitness_values: Vec<CandidateFitness> = candidates
+            .into_iter()
+            .zip(fitness)
+            .map(|(candidate, fitness)| CandidateFitness { candidate, fitness })
+            .collect();
+        // A stable sort, so that candidates with the same fitness keep their order
         fitness_values.sort_by_key(|candidate_with_fitness| candidate_with_fitness.fitness);
         Ok(fitness_values)
     }
 
     // Generates a a random population for a given data set
-    pub fn generate(spec: &GaSpec) -> Self {
-        let mut candidates = HashSet::with_capacity(spec.initial_generation.candidates.max);
+    pub fn generate<T: Rng>(rng: &mut T, spec: &GaSpec) -> Self {
+        let mut candidates = CandidateSet::with_capacity_and_hasher(
+            spec.initial_generation.candidates.max,
+            Default::default(),
+        );
         let mut consecutive_fails = 0;
-        let mut rng = rand::thread_rng();
         let number_of_candidates = rng.gen_range(
             spec.initial_generation.candidates.min,
             spec.initial_generation.candidates.max,
         );
         while candidates.len() < number_of_candidates {
-            let mut candidate = Candidate::generate(&mut rng, spec);
+            let mut candidate = Candidate::generate(rng, spec);
 
             candidate.set_birth_generation_id(0);
 
@@ -113,8 +153,116 @@ impl Population {
     }
 }
 
-impl std::convert::AsRef<HashSet<Candidate>> for Population {
-    fn as_ref(&self) -> &HashSet<Candidate> {
+/// Calculates the fitness of the candidates with the fitness function of the spec, keeping their
+/// order. Only the candidates missing from the cache are evaluated, divided between the threads of
+/// the spec
+pub fn calculate_cached(
+    candidates: &[&Candidate],
+    data_set: &DataSet,
+    spec: &GaSpec,
+    cache: &mut FitnessCache,
+) -> Result<Vec<Fitness>, FitnessCalculationError> {
+    let cached: Vec<Option<Fitness>> = candidates
+        .iter()
+        .map(|candidate| cache.get(candidate))
+        .collect();
+    let missing: Vec<&Candidate> = candidates
+        .iter()
+        .zip(&cached)
+        .filter(|(_, fitness)| fitness.is_none())
+        .map(|(candidate, _)| *candidate)
+        .collect();
+    let calculated = calculate_all(&missing, data_set, &spec.fitness, spec.threads)?;
+    for (candidate, fitness) in missing.iter().zip(&calculated) {
+        cache.insert((*candidate).clone(), *fitness);
+    }
+
+    let mut calculated = calculated.into_iter();
+    Ok(cached
+        .into_iter()
+        .map(|fitness| match fitness {
+            Some(fitness) => fitness,
+            None => calculated.next().unwrap(),
+        })
+        .collect())
+}
+
+/// Evaluates the candidates on `threads` threads, keeping their order
+fn calculate_all(
+    candidates: &[&Candidate],
+    data_set: &DataSet,
+    fitness: &FitnessSpec,
+    threads: usize,
+) -> Result<Vec<Fitness>, FitnessCalculationError> {
+    if threads <= 1 || candidates.len() <= 1 {
+        return candidates
+            .iter()
+            .map(|candidate| fitness.calculate(candidate, data_set))
+            .collect();
+    }
+    let chunk_size = candidates.len().div_ceil(threads);
+    let chunks = thread::scope(|scope| {
+        let handles: Vec<_> = candidates
+            .chunks(chunk_size)
+            .map(|chunk| {
+                scope.spawn(move || {
+                    chunk
+                        .iter()
+                        .map(|candidate| fitness.calculate(candidate, data_set))
+                        .collect::<Result<Vec<Fitness>, FitnessCalculationError>>()
+                })
+            })
+            .collect();
+        // Joined in the order the chunks were handed out, so that the order of the candidates is
+        // kept
+        handles
+            .into_iter()
+            .map(|handle| handle.join().expect("fitness thread panicked"))
+            .collect::<Result<Vec<Vec<Fitness>>, FitnessCalculationError>>()
+    })?;
+    Ok(chunks.into_iter().flatten().collect())
+}
+
+impl std::convert::AsRef<CandidateSet> for Population {
+    fn as_ref(&self) -> &CandidateSet {
         &self.candidates
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rand::SeedableRng;
+    use rand_pcg::Pcg64;
+
+    #[test]
+    fn test_threaded_fitness() {
+        let data_set = DataSet::from_file("data/data2.txt").unwrap();
+        let mut spec = GaSpec::for_tests(&data_set);
+        let population = Population::generate(&mut Pcg64::seed_from_u64(3), &spec);
+
+        let sequential = population
+            .calculate_fitness(&data_set, &spec, &mut FitnessCache::new(0))
+            .unwrap();
+        for threads in 2..6 {
+            spec.threads = threads;
+            assert_eq!(
+                population
+                    .calculate_fitness(&data_set, &spec, &mut FitnessCache::new(0))
+                    .unwrap(),
+                sequential
+            );
+        }
+
+        let mut cache = FitnessCache::new(population.len());
+        for _ in 0..2 {
+            assert_eq!(
+                population
+                    .calculate_fitness(&data_set, &spec, &mut cache)
+                    .unwrap(),
+                sequential
+            );
+        }
+        assert_eq!(cache.hits(), population.len());
+    }
+}
@@ -0,0 +1,430 @@
+use crate::candidate::{Candidate, CandidateFitness, FitnessCalculationError};
+use crate::dataset::DataSet;
+use crate::fitness_cache::FitnessCache;
+use crate::ga_spec::GaSpec;
+use crate::pareto;
+use crate::population::{self, CandidateSet, Population};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+/// Decides which candidates make it into the next generation once the offsprings are created
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReplacementStrategy {
+    #[serde(flatten)]
+    pub options: ReplacementStrategyCommonOptions,
+    #[serde(flatten)]
+    pub variant: ReplacementStrategyVariant,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReplacementStrategyCommonOptions {
+    /// The number of candidates the population is trimmed down to every generation
+    pub population_size: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type")]
+#[serde(rename_all = "snake_case")]
+pub enum ReplacementStrategyVariant {
+    /// The offsprings replace the parents. If there are fewer offsprings than the population
+    /// size, the fittest parents fill the remaining slots
+    Generational,
+    /// Each offspring replaces the least fit parent
+    SteadyState,
+    /// The fittest candidates out of the parents and the offsprings survive
+    MuPlusLambda,
+    /// The fittest offsprings survive. If there are fewer offsprings than the population size,
+    /// the fittest parents fill the remaining slots
+    MuCommaLambda,
+    /// Parents older than `max_age` generations are culled, and the fittest of the rest and the
+    /// offsprings survive
+    AgeBased { max_age: usize },
+    /// The parents and the offsprings are sorted into non-dominated fronts over the objectives of
+    /// the spec, and survive front by front. The last front that fits only partially is cut by
+    /// crowding distance
+    Nsga2,
+}
+
+#[derive(Error, Debug)]
+pub enum ReplacementError {
+    #[error("population size cannot be zero")]
+    ZeroPopulationSize,
+
+    #[error(transparent)]
+    FitnessCalculationError(#[from] FitnessCalculationError),
+}
+
+impl ReplacementStrategy {
+    /// Replaces the candidates of the population with the survivors out of the population and the
+    /// offsprings. The elites always survive. The fitness of the candidates is looked up in the
+    /// cache, so only the offsprings are evaluated.
+    /// Returns the number of candidates that were removed from the population
+    pub fn replace(
+        &self,
+        population: &mut Population,
+        offsprings: Vec<Candidate>,
+        elites: &[Candidate],
+        data_set: &DataSet,
+        spec: &GaSpec,
+        cache: &mut FitnessCache,
+    ) -> Result<usize, ReplacementError> {
+        let population_size = self.options.population_size;
+        if population_size == 0 {
+            return Err(ReplacementError::ZeroPopulationSize);
+        }
+
+        let generation = population.generation();
+
+        let mut offsprings: Vec<Candidate> = offsprings
+            .into_iter()
+            .filter(|offspring| !population.contains(offspring))
+            .map(|mut offspring| {
+                offspring.set_birth_generation_id(generation);
+                offspring
+            })
+            .collect();
+
+        let mut parents: Vec<Candidate> = population.candidates().iter().cloned().collect();
+
+        let survivors = match &self.variant {
+            ReplacementStrategyVariant::Generational => {
+                sort_by_fitness_descending(&mut parents, data_set, spec, cache)?;
+                offsprings.into_iter().chain(parents).collect()
+            }
+            ReplacementStrategyVariant::SteadyState => {
+                sort_by_fitness_descending(&mut parents, data_set, spec, cache)?;
+                let vacant = population_size.saturating_sub(parents.len());
+                let replaced =
+                    std::cmp::min(offsprings.len().saturating_sub(vacant), parents.len());
+                parents.truncate(parents.len() - replaced);
+                // If there are more offsprings than the population size, only the fittest get in
+                sort_by_fitness_descending(&mut offsprings, data_set, spec, cache)?;
+                offsprings.into_iter().chain(parents).collect()
+            }
+            ReplacementStrategyVariant::MuPlusLambda => {
+                let mut pool: Vec<Candidate> = parents.into_iter().chain(offsprings).collect();
+                sort_by_fitness_descending(&mut pool, data_set, spec, cache)?;
+                pool
+            }
+            ReplacementStrategyVariant::MuCommaLambda => {
+                sort_by_fitness_descending(&mut offsprings, data_set, spec, cache)?;
+                sort_by_fitness_descending(&mut parents, data_set, spec, cache)?;
+                offsprings.into_iter().chain(parents).collect()
+            }
+            ReplacementStrategyVariant::AgeBased { max_age } => {
+                parents.retain(|parent| {
+                    parent
+                        .age(generation)
+                        .map(|age| age <= *max_age as isize)
+                        .unwrap_or(true)
+                });
+                let mut pool: Vec<Candidate> = parents.into_iter().chain(offsprings).collect();
+                sort_by_fitness_descending(&mut pool, data_set, spec, cache)?;
+                pool
+            }
+            ReplacementStrategyVariant::Nsga2 => {
+                let mut pool: Vec<Candidate> = parents.into_iter().chain(offsprings).collect();
+                sort_by_crowded_comparison(&mut pool, data_set, spec, cache)?;
+                pool
+            }
+        };
+
+        let mut candidates =
+            CandidateSet::with_capacity_and_hasher(population_size, Default::default());
+        for elite in elites.iter().take(population_size) {
+            candidates.insert(elite.clone());
+        }
+        for survivor in survivors {
+            if candidates.len() >= population_size {
+                break;
+            }
+            candidates.insert(survivor);
+        }
+
+        let removed = population
+            .candidates()
+            .iter()
+            .filter(|candidate| !candidates.contains(*candidate))
+            .count();
+
+        *population.candidates_mut() = candidates;
+        Ok(removed)
+    }
+}
+
+fn sort_by_fitness_descending(
+    candidates: &mut Vec<Candidate>,
+    data_set: &DataSet,
+    spec: &GaSpec,
+    cache: &mut FitnessCache,
+) -> Result<(), FitnessCalculationError> {
+    let references: Vec<&Candidate> = candidates.iter().collect();
+    let fitness = population::calculate_cached(&references, data_set, spec, cache)?;
+    let mut with_fitness: Vec<_> = fitness.into_iter().zip(candidates.drain(..)).collect();
+    with_fitness.sort_by(|(a, _), (b, _)| b.cmp(a));
+    candidates.extend(with_fitness.into_iter().map(|(_, candidate)| candidate));
+    Ok(())
+}
+
+fn sort_by_crowded_comparison(
+    candidates: &mut Vec<Candidate>,
+    data_set: &DataSet,
+    spec: &GaSpec,
+    cache: &mut FitnessCache,
+) -> Result<(), FitnessCalculationError> {
+    let references: Vec<&Candidate> = candidates.iter().collect();
+    let fitness = population::calculate_cached(&references, data_set, spec, cache)?;
+    let with_fitness: Vec<CandidateFitness> = references
+        .into_iter()
+        .zip(fitness)
+        .map(|(candidate, fitness)| CandidateFitness { candidate, fitness })
+        .collect();
+    let order = pareto::crowded_order(&with_fitness, &spec.objectives);
+    let mut sorted: Vec<Option<Candidate>> = candidates.drain(..).map(Some).collect();
+    candidates.extend(order.into_iter().filter_map(|index| sorted[index].take()));
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::rule::{Constraint, Rule};
+    use std::collections::BTreeMap;
+
+    fn data_set() -> DataSet {
+        let mut data_set = DataSet::new();
+        for line in &["000 0", "001 0", "010 0", "011 0"] {
+            data_set.push(line.parse().unwrap()).unwrap();
+        }
+        data_set
+    }
+
+    fn spec(data_set: &DataSet) -> GaSpec {
+        GaSpec::for_tests(data_set)
+    }
+
+    fn rule(input: &str, output: &str) -> Rule {
+        let constraints: BTreeMap<usize, Constraint> = input
+            .chars()
+            .map(Constraint::Character)
+            .enumerate()
+            .collect();
+        Rule::new(constraints, output.to_owned())
+    }
+
+    /// A candidate that classifies `correct` of the four items correctly. Each variant adds a rule
+    /// that never fires, to tell apart candidates with the same fitness
+    fn candidate(correct: usize, variant: usize) -> Candidate {
+        let wrong = ["000", "001", "010", "011"].iter().take(4 - correct);
+        let never = ["100", "101", "110", "111"].iter().take(variant);
+        Candidate::from_rules(
+            wrong.chain(never).map(|input| rule(input, "1")).collect(),
+            "0".to_owned(),
+        )
+    }
+
+    fn population(candidates: &[Candidate]) -> Population {
+        let mut population = Population::default();
+        *population.candidates_mut() = candidates.iter().cloned().collect();
+        population
+    }
+
+    fn strategy(
+        variant: ReplacementStrategyVariant,
+        population_size: usize,
+    ) -> ReplacementStrategy {
+        ReplacementStrategy {
+            options: ReplacementStrategyCommonOptions { population_size },
+            variant,
+        }
+    }
+
+    /// Replaces the parents with a population size of 3
+    fn replace(
+        variant: ReplacementStrategyVariant,
+        parents: &[Candidate],
+        offsprings: &[Candidate],
+        elites: &[Candidate],
+    ) -> Population {
+        let data_set = data_set();
+        let spec = spec(&data_set);
+        let mut population = population(parents);
+        population.set_generation(5);
+        strategy(variant, 3)
+            .replace(
+                &mut population,
+                offsprings.to_vec(),
+                elites,
+                &data_set,
+                &spec,
+                &mut FitnessCache::new(16),
+            )
+            .unwrap();
+        population
+    }
+
+    fn assert_survivors(population: &Population, expected: &[Candidate]) {
+        assert_eq!(population.len(), expected.len());
+        for candidate in expected {
+            assert!(population.contains(candidate), "{:?} is missing", candidate);
+        }
+    }
+
+    fn parents() -> Vec<Candidate> {
+        vec![candidate(1, 0), candidate(2, 0), candidate(3, 0)]
+    }
+
+    #[test]
+    fn test_generational() {
+        let offsprings = [candidate(1, 1), candidate(2, 1)];
+        let population = replace(
+            ReplacementStrategyVariant::Generational,
+            &parents(),
+            &offsprings,
+            &[],
+        );
+        assert_survivors(
+            &population,
+            &[candidate(1, 1), candidate(2, 1), candidate(3, 0)],
+        );
+
+        // The elite survives even though it is the least fit
+        let population = replace(
+            ReplacementStrategyVariant::Generational,
+            &parents(),
+            &offsprings,
+            &[candidate(1, 0)],
+        );
+        assert_survivors(
+            &population,
+            &[candidate(1, 0), candidate(1, 1), candidate(2, 1)],
+        );
+    }
+
+    #[test]
+    fn test_steady_state() {
+        // The offsprings take the places of the two least fit parents
+        let population = replace(
+            ReplacementStrategyVariant::SteadyState,
+            &parents(),
+            &[candidate(1, 1), candidate(2, 1)],
+            &[],
+        );
+        assert_survivors(
+            &population,
+            &[candidate(1, 1), candidate(2, 1), candidate(3, 0)],
+        );
+    }
+
+    #[test]
+    fn test_mu_plus_lambda() {
+        let population = replace(
+            ReplacementStrategyVariant::MuPlusLambda,
+            &parents(),
+            &[candidate(4, 0), candidate(1, 1)],
+            &[],
+        );
+        assert_survivors(
+            &population,
+            &[candidate(4, 0), candidate(3, 0), candidate(2, 0)],
+        );
+    }
+
+    #[test]
+    fn test_mu_comma_lambda() {
+        // The offsprings survive ahead of fitter parents
+        let population = replace(
+            ReplacementStrategyVariant::MuCommaLambda,
+            &parents(),
+            &[candidate(1, 1), candidate(2, 1)],
+            &[],
+        );
+        assert_survivors(
+            &population,
+            &[candidate(1, 1), candidate(2, 1), candidate(3, 0)],
+        );
+    }
+
+    #[test]
+    fn test_age_based() {
+        // The population is in generation 5, so the fittest parent is too old to survive
+        let mut parents = parents();
+        parents[0].set_birth_generation_id(5);
+        parents[1].set_birth_generation_id(4);
+        parents[2].set_birth_generation_id(1);
+        let population = replace(
+            ReplacementStrategyVariant::AgeBased { max_age: 1 },
+            &parents,
+            &[candidate(1, 1)],
+            &[],
+        );
+        assert_survivors(
+            &population,
+            &[candidate(1, 0), candidate(2, 0), candidate(1, 1)],
+        );
+    }
+
+    #[test]
+    fn test_offsprings_only_are_evaluated() {
+        let data_set = data_set();
+        let spec = spec(&data_set);
+        let mut population = population(&[candidate(1, 0), candidate(2, 0), candidate(3, 0)]);
+        let mut cache = FitnessCache::new(16);
+        population
+            .calculate_fitness(&data_set, &spec, &mut cache)
+            .unwrap();
+        let misses = cache.misses();
+
+        let offsprings = vec![candidate(4, 0), candidate(1, 1)];
+        strategy(ReplacementStrategyVariant::MuPlusLambda, 3)
+            .replace(
+                &mut population,
+                offsprings,
+                &[],
+                &data_set,
+                &spec,
+                &mut cache,
+            )
+            .unwrap();
+        assert_eq!(cache.misses() - misses, 2);
+        assert!(population.contains(&candidate(4, 0)));
+    }
+
+    #[test]
+    fn test_nsga2() {
+        let data_set = data_set();
+        let spec = spec(&data_set);
+        // Classifies the first `correct` items correctly with a rule each, so that fitness comes
+        // at the cost of rules
+        let fixer = |correct: usize, never: &[&str]| {
+            let inputs = ["000", "001", "010", "011"];
+            Candidate::from_rules(
+                inputs[..correct]
+                    .iter()
+                    .chain(never)
+                    .map(|input| rule(input, "0"))
+                    .collect(),
+                "1".to_owned(),
+            )
+        };
+        // The first front is the candidate with no rules and every item correct. The second has
+        // the fixers, where the ends are kept and 2 rules is less crowded than 1. The fixer with a
+        // rule that never fires is on the third front
+        let mut population = population(&[fixer(0, &[]), fixer(1, &[]), fixer(2, &["100"])]);
+        let offsprings = vec![candidate(4, 0), fixer(2, &[]), fixer(4, &[])];
+        strategy(ReplacementStrategyVariant::Nsga2, 4)
+            .replace(
+                &mut population,
+                offsprings,
+                &[],
+                &data_set,
+                &spec,
+                &mut FitnessCache::new(16),
+            )
+            .unwrap();
+        assert_survivors(
+            &population,
+            &[candidate(4, 0), fixer(0, &[]), fixer(2, &[]), fixer(4, &[])],
+        );
+    }
+}
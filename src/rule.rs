@@ -1,110 +1,436 @@
-use crate::ga_spec::GaSpec;
-use rand::{self, Rng};
-use std::collections::HashMap;
+use crate::bits::Bits;
+use crate::dataitem::DataItemInput;
+use crate::ga_spec::{GaSpec, RepresentationSpec};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::collections::btree_map::{BTreeMap, Entry};
 use std::fmt::{self, Debug, Display};
 use std::hash::{Hash, Hasher};
-use std::string::ToString;
 use thiserror::Error;
 
-/// A rule is a list of checks to do to yield 1
-#[derive(Eq, PartialEq, Clone)]
+/// A single check done against the value at an index of the input
+#[derive(Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Constraint {
+    /// The character at the index has to match exactly
+    Character(char),
+    /// The value at the index has to be within the bounds(inclusive)
+    Interval { lower: f64, upper: f64 },
+}
+
+/// The bits of an interval bound, with `-0.0` taken as `0.0` since the two bound the same values
+fn bound_bits(bound: f64) -> u64 {
+    if bound == 0.0 {
+        0.0f64.to_bits()
+    } else {
+        bound.to_bits()
+    }
+}
+
+// Bounds are compared by their bits, so that equality agrees with the hash
+impl PartialEq for Constraint {
+    fn eq(&self, rhs: &Constraint) -> bool {
+        match (self, rhs) {
+            (Constraint::Character(character), Constraint::Character(rhs_character)) => {
+                character == rhs_character
+            }
+            (
+                Constraint::Interval { lower, upper },
+                Constraint::Interval {
+                    lower: rhs_lower,
+                    upper: rhs_upper,
+                },
+            ) => {
+                bound_bits(*lower) == bound_bits(*rhs_lower)
+                    && bound_bits(*upper) == bound_bits(*rhs_upper)
+            }
+            _ => false,
+        }
+    }
+}
+
+impl Eq for Constraint {}
+
+impl Hash for Constraint {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        std::mem::discriminant(self).hash(state);
+        match self {
+            Constraint::Character(character) => character.hash(state),
+            Constraint::Interval { lower, upper } => {
+                bound_bits(*lower).hash(state);
+                bound_bits(*upper).hash(state);
+            }
+        }
+    }
+}
+
+impl Constraint {
+    pub fn matches(
+        &self,
+        input: &DataItemInput,
+        index: usize,
+    ) -> Result<bool, RuleEvaluationError> {
+        if index >= input.width() {
+            return Err(RuleEvaluationError::IndexOutOfRange);
+        }
+        match self {
+            Constraint::Character(character) => Ok(input
+                .char_at(index)
+                .ok_or(RuleEvaluationError::RepresentationMismatch)?
+                == *character),
+            Constraint::Interval { lower, upper } => {
+                let value = input
+                    .real_at(index)
+                    .ok_or(RuleEvaluationError::RepresentationMismatch)?;
+                Ok(*lower <= value && value <= *upper)
+            }
+        }
+    }
+
+    /// Generates a random constraint on the feature at `index` for the representation in the spec
+    pub fn generate<T: Rng>(rng: &mut T, spec: &GaSpec, index: usize) -> Self {
+        match spec.representation {
+            RepresentationSpec::Binary => {
+                let character_index = rng.gen_range(0, spec.calculated.alphabet.len());
+                Constraint::Character(
+                    spec.calculated
+                        .alphabet
+                        .chars()
+                        .nth(character_index)
+                        .unwrap(),
+                )
+            }
+            RepresentationSpec::Interval {
+                min_width,
+                max_width,
+            } => {
+                let (min, max) = spec.calculated.value_ranges[index];
+                let width = if min_width < max_width {
+                    rng.gen_range(min_width, max_width)
+                } else {
+                    min_width
+                } * (max - min);
+                let center = if min < max {
+                    rng.gen_range(min, max)
+                } else {
+                    min
+                };
+                Constraint::Interval {
+                    lower: (center - width / 2.0).max(min),
+                    upper: (center + width / 2.0).min(max),
+                }
+            }
+        }
+    }
+
+    /// Moves both of the bounds of an interval by the same offset, without leaving the range
+    pub fn creep(&mut self, offset: f64, (min, max): (f64, f64)) {
+        if let Constraint::Interval { lower, upper } = self {
+            let offset = offset.max(min - *lower).min(max - *upper);
+            *lower += offset;
+            *upper += offset;
+        }
+    }
+
+    /// Moves the bounds of an interval away from each other. Negative values narrow the interval
+    pub fn widen(&mut self, lower_delta: f64, upper_delta: f64, (min, max): (f64, f64)) {
+        if let Constraint::Interval { lower, upper } = self {
+            let new_lower = (*lower - lower_delta).max(min);
+            let new_upper = (*upper + upper_delta).min(max);
+            if new_lower <= new_upper {
+                *lower = new_lower;
+                *upper = new_upper;
+            } else {
+                // Collapse to the midpoint instead of inverting the bounds
+                let midpoint = (new_lower + new_upper) / 2.0;
+                *lower = midpoint;
+                *upper = midpoint;
+            }
+        }
+    }
+}
+
+impl Display for Constraint {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Constraint::Character(character) => write!(formatter, "{}", character),
+            Constraint::Interval { lower, upper } => {
+                write!(formatter, "[{:.6},{:.6}]", lower, upper)
+            }
+        }
+    }
+}
+
+/// A rule is a list of checks to do to yield its output
+#[derive(Eq, PartialEq, Hash, Clone, Serialize, Deserialize)]
 pub struct Rule {
-    constraints: HashMap<usize, char>,
+    constraints: BTreeMap<usize, Constraint>,
+    output: String,
+}
+
+/// A binary rule compiled to a pair of masks, so that it can be checked against a packed input a
+/// word at a time. `care` has the bits that are constrained set, `value` has the bits they have to
+/// equal
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BitRule {
+    care: Bits,
+    value: Bits,
+}
+
+impl BitRule {
+    pub fn matches(&self, input: &Bits) -> bool {
+        input.matches(&self.care, &self.value)
+    }
 }
 
 #[derive(Error, Debug, Eq, PartialEq, Clone, Copy)]
 pub enum RuleEvaluationError {
     #[error("the rule contains constraints that is out of the index range of the input")]
     IndexOutOfRange,
+
+    #[error("the rule contains constraints that cannot be checked against the input")]
+    RepresentationMismatch,
 }
 
 impl Rule {
-    pub fn constraints(&self) -> &HashMap<usize, char> {
+    pub fn new(constraints: BTreeMap<usize, Constraint>, output: String) -> Self {
+        Rule {
+            constraints,
+            output,
+        }
+    }
+
+    pub fn constraints(&self) -> &BTreeMap<usize, Constraint> {
         &self.constraints
     }
 
-    pub fn constraints_mut(&mut self) -> &mut HashMap<usize, char> {
+    pub fn constraints_mut(&mut self) -> &mut BTreeMap<usize, Constraint> {
         &mut self.constraints
     }
 
-    pub fn evaluate(&self, input: &str) -> Result<bool, RuleEvaluationError> {
-        for (index, character) in &self.constraints {
-            if input
-                .chars()
-                .nth(*index)
-                .ok_or(RuleEvaluationError::IndexOutOfRange)?
-                != *character
-            {
+    /// The class predicted for an input that passes all the checks
+    pub fn output(&self) -> &str {
+        &self.output
+    }
+
+    pub fn set_output(&mut self, output: String) {
+        self.output = output;
+    }
+
+    /// Checks whether the input passes all the checks of the rule
+    pub fn evaluate(&self, input: &DataItemInput) -> Result<bool, RuleEvaluationError> {
+        for (index, constraint) in &self.constraints {
+            if !constraint.matches(input, *index)? {
                 return Ok(false);
             }
         }
         Ok(true)
     }
 
+    /// Compiles the rule for binary inputs of `width` digits. Returns none if any of the
+    /// constraints is not a binary digit or is out of range, in which case the rule has to be
+    /// evaluated constraint by constraint
+    pub fn compile(&self, width: usize) -> Option<BitRule> {
+        let mut care = Bits::zeros(width);
+        let mut value = Bits::zeros(width);
+        for (index, constraint) in &self.constraints {
+            if *index >= width {
+                return None;
+            }
+            let bit = match constraint {
+                Constraint::Character('0') => false,
+                Constraint::Character('1') => true,
+                _ => return None,
+            };
+            care.set(*index, true);
+            value.set(*index, bit);
+        }
+        Some(BitRule { care, value })
+    }
+
     pub fn len(&self) -> usize {
         self.constraints.len()
     }
 
-    pub fn generate<T: Rng>(mut rng: &mut T, spec: &GaSpec) -> Self {
+    pub fn is_empty(&self) -> bool {
+        self.constraints.is_empty()
+    }
+
+    pub fn generate<T: Rng>(rng: &mut T, spec: &GaSpec) -> Self {
         let number_of_constraints: usize = rng.gen_range(
             spec.initial_generation.constraints.min,
             spec.initial_generation.constraints.max,
         );
-        let mut constraints = HashMap::with_capacity(number_of_constraints);
+        let mut constraints = BTreeMap::new();
 
         let mut consecutive_fails = 0;
 
         while constraints.len() < number_of_constraints {
             let index: usize = rng.gen_range(0, spec.calculated.max_index);
 
-            if constraints.contains_key(&index) {
+            if let Entry::Vacant(entry) = constraints.entry(index) {
+                consecutive_fails = 0;
+                entry.insert(Constraint::generate(rng, spec, index));
+            } else {
                 consecutive_fails += 1;
                 if consecutive_fails >= spec.initial_generation.constraints.rng_fail_retries {
                     break;
                 }
-            } else {
-                consecutive_fails = 0;
-                let character_index = rng.gen_range(0, spec.calculated.alphabet.len());
-                constraints.insert(
-                    index,
-                    spec.calculated
-                        .alphabet
-                        .chars()
-                        .nth(character_index)
-                        .unwrap(),
-                );
             }
         }
-        Rule { constraints }
+        Rule {
+            constraints,
+            output: generate_output(rng, spec),
+        }
     }
 }
 
+/// Picks a random output out of the classes found in the data
+pub fn generate_output<T: Rng>(rng: &mut T, spec: &GaSpec) -> String {
+    let output_index = rng.gen_range(0, spec.calculated.classes.len());
+    spec.calculated.classes[output_index].clone()
+}
+
 impl Debug for Rule {
     fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
         formatter.write_str(&self.to_string())
     }
 }
 
-impl ToString for Rule {
-    fn to_string(&self) -> String {
-        let mut output = String::with_capacity(self.constraints.len());
-        let mut entries: Vec<(&usize, &char)> = self.constraints.iter().collect();
+impl Display for Rule {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let entries: Vec<(&usize, &Constraint)> = self.constraints.iter().collect();
 
-        entries.sort_by_key(|(&index, _)| index);
+        let all_characters = entries
+            .iter()
+            .all(|(_, constraint)| matches!(constraint, Constraint::Character(_)));
 
-        for (index, character) in entries.into_iter() {
-            while *index > output.len() {
-                output.push('_');
+        if self.constraints.is_empty() {
+            formatter.write_str("*")?;
+        } else if all_characters {
+            // Binary rules are written out positionally, with a '_' for a wildcard
+            let mut position = 0;
+            for (index, constraint) in entries.into_iter() {
+                while *index > position {
+                    formatter.write_str("_")?;
+                    position += 1;
+                }
+                write!(formatter, "{}", constraint)?;
+                position += 1;
+            }
+        } else {
+            for (position, (index, constraint)) in entries.into_iter().enumerate() {
+                if position > 0 {
+                    formatter.write_str(" ")?;
+                }
+                write!(formatter, "{}:{}", index, constraint)?;
             }
-            output.push(*character);
         }
-        output
+        write!(formatter, " => {}", self.output)
     }
 }
 
-impl Hash for Rule {
-    fn hash<H: Hasher>(&self, state: &mut H) {
-        let as_str = format!("{:?}", self);
-        as_str.hash(state);
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::dataset::DataSet;
+    use rand::SeedableRng;
+    use rand_pcg::Pcg64;
+    use std::collections::hash_map::DefaultHasher;
+
+    fn hash(rule: &Rule) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        rule.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    #[test]
+    fn test_signed_zero_bounds() {
+        let rule = |lower: f64| {
+            let mut constraints = BTreeMap::new();
+            constraints.insert(0, Constraint::Interval { lower, upper: 1.0 });
+            Rule::new(constraints, "1".to_owned())
+        };
+        assert_eq!(rule(0.0), rule(-0.0));
+        assert_eq!(hash(&rule(0.0)), hash(&rule(-0.0)));
+        assert_ne!(rule(0.0), rule(0.5));
+    }
+
+    fn interval(lower: f64, upper: f64) -> Constraint {
+        Constraint::Interval { lower, upper }
+    }
+
+    #[test]
+    fn test_matches() {
+        let real = DataItemInput::Real(vec![0.5, 2.0]);
+        assert_eq!(interval(0.5, 1.0).matches(&real, 0), Ok(true));
+        assert_eq!(interval(0.0, 0.5).matches(&real, 0), Ok(true));
+        assert_eq!(interval(0.6, 1.0).matches(&real, 0), Ok(false));
+        assert_eq!(interval(0.0, 1.0).matches(&real, 1), Ok(false));
+        assert_eq!(
+            interval(0.0, 1.0).matches(&real, 2),
+            Err(RuleEvaluationError::IndexOutOfRange)
+        );
+        assert_eq!(
+            Constraint::Character('1').matches(&real, 0),
+            Err(RuleEvaluationError::RepresentationMismatch)
+        );
+
+        let binary = DataItemInput::Binary("01".parse().unwrap());
+        assert_eq!(Constraint::Character('1').matches(&binary, 1), Ok(true));
+        assert_eq!(Constraint::Character('1').matches(&binary, 0), Ok(false));
+        assert_eq!(
+            Constraint::Character('1').matches(&binary, 2),
+            Err(RuleEvaluationError::IndexOutOfRange)
+        );
+    }
+
+    #[test]
+    fn test_creep() {
+        let mut constraint = interval(0.2, 0.4);
+        constraint.creep(0.1, (0.0, 1.0));
+        assert_eq!(constraint.to_string(), "[0.300000,0.500000]");
+
+        // The width is kept when the offset would take a bound out of the range
+        let mut constraint = interval(0.2, 0.4);
+        constraint.creep(0.9, (0.0, 1.0));
+        assert_eq!(constraint.to_string(), "[0.800000,1.000000]");
+        constraint.creep(-2.0, (0.0, 1.0));
+        assert_eq!(constraint.to_string(), "[0.000000,0.200000]");
+    }
+
+    #[test]
+    fn test_widen() {
+        let mut constraint = interval(0.4, 0.6);
+        constraint.widen(0.1, 0.5, (0.0, 1.0));
+        assert_eq!(constraint.to_string(), "[0.300000,1.000000]");
+
+        // Narrowing past each other collapses to the midpoint rather than inverting the bounds
+        let mut constraint = interval(0.4, 0.6);
+        constraint.widen(-0.3, -0.3, (0.0, 1.0));
+        assert_eq!(constraint.to_string(), "[0.500000,0.500000]");
+    }
+
+    #[test]
+    fn test_generate_within_range() {
+        let data_set =
+            DataSet::from_reader("2 rows x 2 variables\n0 60 a\n2 96 b\n".as_bytes()).unwrap();
+        let mut spec = GaSpec::for_tests(&data_set);
+        spec.representation = RepresentationSpec::Interval {
+            min_width: 0.1,
+            max_width: 0.5,
+        };
+        let mut rng = Pcg64::seed_from_u64(1);
+        for (index, (min, max)) in [(0.0, 2.0), (60.0, 96.0)].iter().enumerate() {
+            for _ in 0..100 {
+                match Constraint::generate(&mut rng, &spec, index) {
+                    Constraint::Interval { lower, upper } => {
+                        assert!(*min <= lower && lower <= upper && upper <= *max);
+                        assert!(upper - lower <= 0.5 * (max - min));
+                    }
+                    constraint => panic!("{} is not an interval", constraint),
+                }
+            }
+        }
     }
 }
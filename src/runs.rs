@@ -0,0 +1,108 @@
+use crate::metrics::Statistics;
+use serde::Serialize;
+
+/// The outcome of one of many independent runs
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RunRecord {
+    pub seed: u64,
+    /// The best training fitness of each generation
+    pub best_fitness: Vec<f64>,
+    /// The first generation that reached the optimum fitness
+    pub optimum_generation: Option<usize>,
+}
+
+/// The best fitness of a generation across all the runs
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct GenerationSummary {
+    pub generation: usize,
+    pub best_fitness: Statistics,
+}
+
+/// Statistics aggregated over a number of independent runs
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RunsSummary {
+    pub runs: Vec<RunRecord>,
+    pub generations: Vec<GenerationSummary>,
+    /// Calculated over the runs that reached the optimum. None if no run did
+    pub generations_to_optimum: Option<Statistics>,
+    /// The ratio of runs that reached the optimum
+    pub success_rate: f64,
+}
+
+impl RunsSummary {
+    /// Runs that stop early, once they reach the optimum, keep their last best fitness for the
+    /// rest of the generations
+    pub fn new(runs: Vec<RunRecord>) -> RunsSummary {
+        let generation_count = runs
+            .iter()
+            .map(|run| run.best_fitness.len())
+            .max()
+            .unwrap_or(0);
+
+        let generations = (0..generation_count)
+            .filter_map(|index| {
+                let values: Vec<f64> = runs
+                    .iter()
+                    .filter_map(|run| {
+                        run.best_fitness
+                            .get(index)
+                            .or_else(|| run.best_fitness.last())
+                    })
+                    .copied()
+                    .collect();
+                Some(GenerationSummary {
+                    generation: index + 1,
+                    best_fitness: Statistics::from_values(&values)?,
+                })
+            })
+            .collect();
+
+        let optimum_generations: Vec<f64> = runs
+            .iter()
+            .filter_map(|run| run.optimum_generation)
+            .map(|generation| generation as f64)
+            .collect();
+        let success_rate = if runs.is_empty() {
+            0.0
+        } else {
+            optimum_generations.len() as f64 / runs.len() as f64
+        };
+
+        RunsSummary {
+            generations_to_optimum: Statistics::from_values(&optimum_generations),
+            generations,
+            runs,
+            success_rate,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_early_stop() {
+        let summary = RunsSummary::new(vec![
+            RunRecord {
+                seed: 0,
+                best_fitness: vec![2.0, 4.0],
+                optimum_generation: Some(2),
+            },
+            RunRecord {
+                seed: 1,
+                best_fitness: vec![1.0, 2.0, 3.0],
+                optimum_generation: None,
+            },
+        ]);
+        assert_eq!(summary.generations.len(), 3);
+        assert_eq!(summary.generations[2].best_fitness.mean, 3.5);
+        assert_eq!(summary.success_rate, 0.5);
+        assert_eq!(
+            summary
+                .generations_to_optimum
+                .map(|statistics| statistics.mean),
+            Some(2.0)
+        );
+    }
+}
@@ -1,10 +1,10 @@
-use crate::candidate::Candidate;
 use crate::candidate::CandidateFitness;
-use rand::{self, Rng};
-use serde::Deserialize;
+use crate::pareto::{self, Objective};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SelectionStrategy {
     #[serde(flatten)]
     pub options: SelectionStrategyCommonOptions,
@@ -13,22 +13,27 @@ pub struct SelectionStrategy {
 }
 
 impl SelectionStrategy {
-    pub fn select<'a>(
+    pub fn select<'a, T: Rng>(
         &'_ self,
-        candidates: &Vec<CandidateFitness<'a>>,
+        rng: &mut T,
+        candidates: &[CandidateFitness<'a>],
+        objectives: &[Objective],
     ) -> Result<Vec<CandidateFitness<'a>>, SelectionError> {
         match &self.variant {
             SelectionStrategyVariant::Tournament(tourney) => {
-                tourney.select(candidates, &self.options)
+                tourney.select(rng, candidates, &self.options)
             }
             SelectionStrategyVariant::Roulette(roulette) => {
-                roulette.select(candidates, &self.options)
+                roulette.select(rng, candidates, &self.options)
+            }
+            SelectionStrategyVariant::Nsga2(nsga2) => {
+                nsga2.select(rng, candidates, &self.options, objectives)
             }
         }
     }
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 #[serde(tag = "setting")]
 pub enum DuplicateHandlingStrategy {
@@ -36,7 +41,7 @@ pub enum DuplicateHandlingStrategy {
     Disallow { retries: usize },
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SelectionStrategyCommonOptions {
     /// selection size
     pub selection_size: usize,
@@ -44,12 +49,14 @@ pub struct SelectionStrategyCommonOptions {
     pub duplicates: DuplicateHandlingStrategy,
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "type")]
 #[serde(rename_all = "snake_case")]
 pub enum SelectionStrategyVariant {
     Roulette(RouletteSelection),
     Tournament(TournamentSelection),
+    /// Tournaments decided by Pareto front and crowding distance over the objectives of the spec
+    Nsga2(Nsga2Selection),
 }
 
 #[derive(Error, Debug)]
@@ -62,59 +69,67 @@ pub enum SelectionError {
 }
 
 pub trait Selection {
-    fn select<'a>(
+    fn select<'a, T: Rng>(
         &'_ self,
-        candidates: &Vec<CandidateFitness<'a>>,
+        rng: &mut T,
+        candidates: &[CandidateFitness<'a>],
         options: &SelectionStrategyCommonOptions,
     ) -> Result<Vec<CandidateFitness<'a>>, SelectionError>;
 }
 
-#[derive(Debug, Clone, Deserialize)]
+/// Picks a random candidate, retrying on the ones already selected if duplicates are disallowed
+fn random_index<T: Rng>(
+    rng: &mut T,
+    candidates: &[CandidateFitness],
+    results: &[CandidateFitness],
+    duplicates: &DuplicateHandlingStrategy,
+) -> Result<usize, SelectionError> {
+    // Rng can fail you
+    let mut failures = 0;
+    loop {
+        let rng = rng.gen_range(0, candidates.len());
+        match duplicates {
+            DuplicateHandlingStrategy::Allow => break Ok(rng),
+            DuplicateHandlingStrategy::Disallow { retries } => {
+                if results.contains(&candidates[rng]) {
+                    failures += 1;
+                    if failures >= *retries {
+                        break Err(SelectionError::RngFail);
+                    }
+                } else {
+                    break Ok(rng);
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TournamentSelection {
     /// The tournament size
     pub tournament_size: usize,
 }
 
 impl Selection for TournamentSelection {
-    fn select<'a>(
+    fn select<'a, T: Rng>(
         &'_ self,
-        candidates: &Vec<CandidateFitness<'a>>,
+        rng: &mut T,
+        candidates: &[CandidateFitness<'a>],
         options: &SelectionStrategyCommonOptions,
     ) -> Result<Vec<CandidateFitness<'a>>, SelectionError> {
         // options.selection_size is the selection size, not the tournament size
         let mut results: Vec<CandidateFitness> = Vec::with_capacity(options.selection_size);
 
-        let mut rng = rand::thread_rng();
-
         // TODO: self.size or options.selection_size could be 0
         // TODO: candidates could be 0
 
         while results.len() < options.selection_size {
             let mut best: Option<&CandidateFitness> = None;
-            for i in 0..self.tournament_size {
-                // Rng can fail you
-                let index = {
-                    let mut failures = 0;
-                    loop {
-                        let rng = rng.gen_range(0, candidates.len());
-                        match options.duplicates {
-                            DuplicateHandlingStrategy::Allow => break Ok(rng),
-                            DuplicateHandlingStrategy::Disallow { retries } => {
-                                if results.contains(&&candidates[rng]) {
-                                    failures += 1;
-                                    if failures >= retries {
-                                        break Err(SelectionError::RngFail);
-                                    }
-                                } else {
-                                    break Ok(rng);
-                                }
-                            }
-                        }
-                    }
-                }?;
+            for _i in 0..self.tournament_size {
+                let index = random_index(rng, candidates, &results, &options.duplicates)?;
 
                 match best {
-                    Some(ref prev_best) => {
+                    Some(prev_best) => {
                         let new_best = &candidates[index];
                         if new_best.fitness > prev_best.fitness {
                             best = Some(new_best)
@@ -125,35 +140,50 @@ impl Selection for TournamentSelection {
             }
 
             // TODO: can best be none?
-            results.push(best.ok_or(SelectionError::EmptyCandidates)?.clone());
+            results.push(*best.ok_or(SelectionError::EmptyCandidates)?);
         }
         Ok(results)
     }
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RouletteSelection;
 
 impl Selection for RouletteSelection {
-    fn select<'a>(
+    fn select<'a, T: Rng>(
         &'_ self,
-        candidates: &Vec<CandidateFitness<'a>>,
+        rng: &mut T,
+        candidates: &[CandidateFitness<'a>],
         options: &SelectionStrategyCommonOptions,
     ) -> Result<Vec<CandidateFitness<'a>>, SelectionError> {
+        if candidates.is_empty() {
+            return Err(SelectionError::EmptyCandidates);
+        }
         let mut results: Vec<CandidateFitness> = Vec::with_capacity(options.selection_size);
-        let mut rng = rand::thread_rng();
-
         // First sum up the fitness values
-        let total: usize = candidates.iter().map(|candidate| candidate.fitness).sum();
-        let mut cumulative_total = 0;
-        let rng = rng.gen_range(0, total);
+        let total: f64 = candidates
+            .iter()
+            .map(|candidate| candidate.fitness.value())
+            .sum();
+        // Parsimony pressure can bring the fitness of every candidate down to zero, which leaves
+        // nothing to weigh the wheel with, so every candidate is as likely as the others
+        if total <= 0.0 {
+            while results.len() < options.selection_size {
+                let index = random_index(rng, candidates, &results, &options.duplicates)?;
+                results.push(candidates[index]);
+            }
+            return Ok(results);
+        }
         let mut failures = 0;
 
         while results.len() < options.selection_size {
+            // Every pick spins the wheel again
+            let threshold = rng.gen_range(0.0, total);
+            let mut cumulative_total = 0.0;
             let mut selected = None;
             for candidate in candidates {
-                cumulative_total += candidate.fitness;
-                if rng < cumulative_total {
+                cumulative_total += candidate.fitness.value();
+                if threshold < cumulative_total {
                     selected = Some(candidate);
                     break;
                 }
@@ -169,10 +199,178 @@ impl Selection for RouletteSelection {
                 }
                 _ => {
                     failures = 0;
-                    results.push(selected.clone());
+                    results.push(*selected);
                 }
             }
         }
         Ok(results)
     }
 }
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Nsga2Selection {
+    /// The tournament size
+    pub tournament_size: usize,
+}
+
+impl Nsga2Selection {
+    /// Runs tournaments where the candidate on the lowest non-dominated front wins, and ties are
+    /// broken by the larger crowding distance
+    pub fn select<'a, T: Rng>(
+        &'_ self,
+        rng: &mut T,
+        candidates: &[CandidateFitness<'a>],
+        options: &SelectionStrategyCommonOptions,
+        objectives: &[Objective],
+    ) -> Result<Vec<CandidateFitness<'a>>, SelectionError> {
+        if candidates.is_empty() {
+            return Err(SelectionError::EmptyCandidates);
+        }
+        let ranks = pareto::rank(candidates, objectives);
+        let mut results: Vec<CandidateFitness> = Vec::with_capacity(options.selection_size);
+
+        while results.len() < options.selection_size {
+            let mut best: Option<usize> = None;
+            for _i in 0..self.tournament_size {
+                let index = random_index(rng, candidates, &results, &options.duplicates)?;
+                best = match best {
+                    Some(prev_best) if ranks[prev_best].crowded_cmp(&ranks[index]).is_le() => {
+                        Some(prev_best)
+                    }
+                    _ => Some(index),
+                };
+            }
+            results.push(candidates[best.ok_or(SelectionError::EmptyCandidates)?]);
+        }
+        Ok(results)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::candidate::Candidate;
+    use crate::fitness::Fitness;
+    use crate::rule::Rule;
+    use rand::SeedableRng;
+    use rand_pcg::Pcg64;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn test_roulette_zero_total() {
+        let candidates: Vec<Candidate> = ["0", "1", "2"]
+            .iter()
+            .map(|output| Candidate::from_rules(vec![], (*output).to_owned()))
+            .collect();
+        let candidates: Vec<CandidateFitness> = candidates
+            .iter()
+            .map(|candidate| CandidateFitness {
+                candidate,
+                fitness: Fitness::new(0.0),
+            })
+            .collect();
+        let options = SelectionStrategyCommonOptions {
+            selection_size: 3,
+            duplicates: DuplicateHandlingStrategy::Disallow { retries: 1000 },
+        };
+
+        let mut selected = RouletteSelection
+            .select(&mut Pcg64::seed_from_u64(1), &candidates, &options)
+            .unwrap();
+        selected.sort_by_key(|selected| selected.candidate.default_output().to_owned());
+        assert_eq!(selected, candidates);
+        assert!(RouletteSelection
+            .select(&mut Pcg64::seed_from_u64(1), &[], &options)
+            .is_err());
+    }
+
+    #[test]
+    fn test_roulette_skewed_wheel() {
+        let candidates: Vec<Candidate> = ["0", "1", "2"]
+            .iter()
+            .map(|output| Candidate::from_rules(vec![], (*output).to_owned()))
+            .collect();
+        let candidates: Vec<CandidateFitness> = candidates
+            .iter()
+            .zip(&[1.0, 0.0, 9.0])
+            .map(|(candidate, fitness)| CandidateFitness {
+                candidate,
+                fitness: Fitness::new(*fitness),
+            })
+            .collect();
+        let mut rng = Pcg64::seed_from_u64(1);
+
+        let options = SelectionStrategyCommonOptions {
+            selection_size: 1000,
+            duplicates: DuplicateHandlingStrategy::Allow,
+        };
+        let selected = RouletteSelection
+            .select(&mut rng, &candidates, &options)
+            .unwrap();
+        let count = |output: &str| {
+            selected
+                .iter()
+                .filter(|selected| selected.candidate.default_output() == output)
+                .count()
+        };
+        assert_eq!(count("1"), 0);
+        assert!(count("0") > 50 && count("0") < 150, "{}", count("0"));
+        assert_eq!(count("0") + count("2"), 1000);
+
+        // Each pick spins the wheel again, so the candidates left can still be reached
+        let options = SelectionStrategyCommonOptions {
+            selection_size: 2,
+            duplicates: DuplicateHandlingStrategy::Disallow { retries: 1000 },
+        };
+        let mut selected = RouletteSelection
+            .select(&mut rng, &candidates, &options)
+            .unwrap();
+        selected.sort_by_key(|selected| selected.candidate.default_output().to_owned());
+        assert_eq!(selected, vec![candidates[0], candidates[2]]);
+    }
+
+    #[test]
+    fn test_nsga2_tournament() {
+        let candidates: Vec<Candidate> = (1..=4)
+            .map(|rules| {
+                Candidate::from_rules(
+                    vec![Rule::new(BTreeMap::new(), "1".to_owned()); rules],
+                    "0".to_owned(),
+                )
+            })
+            .collect();
+        // Fitness and rule count of (1, 1), (3, 2), (2, 3) and (4, 4)
+        let candidates: Vec<CandidateFitness> = candidates
+            .iter()
+            .zip(&[1.0, 3.0, 2.0, 4.0])
+            .map(|(candidate, fitness)| CandidateFitness {
+                candidate,
+                fitness: Fitness::new(*fitness),
+            })
+            .collect();
+        let objectives = [Objective::Fitness, Objective::Rules];
+        let options = SelectionStrategyCommonOptions {
+            selection_size: 4,
+            duplicates: DuplicateHandlingStrategy::Disallow { retries: 1000 },
+        };
+        let selection = Nsga2Selection {
+            tournament_size: 100,
+        };
+
+        // Tournaments this large take in every candidate not selected yet, so each one is won by
+        // the best of the rest by crowded comparison. The ends of the first front tie
+        let selected: Vec<f64> = selection
+            .select(
+                &mut Pcg64::seed_from_u64(1),
+                &candidates,
+                &options,
+                &objectives,
+            )
+            .unwrap()
+            .iter()
+            .map(|selected| selected.fitness.value())
+            .collect();
+        assert!(selected[..2].contains(&1.0) && selected[..2].contains(&4.0));
+        assert_eq!(selected[2..], [3.0, 2.0]);
+    }
+}
@@ -0,0 +1,204 @@
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::path::Path;
+use thiserror::Error;
+
+/// The state of the population at the end of a generation
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GenerationStats {
+    pub generation: usize,
+    /// The size of the population when its fitness was calculated
+    pub size: usize,
+    pub min_fitness: f64,
+    pub average_fitness: f64,
+    pub max_fitness: f64,
+    pub median_fitness: f64,
+    pub fitness_stddev: f64,
+    /// The ratio of distinct rules to all the rules in the population
+    pub diversity: f64,
+    /// The number of candidates selected for crossover
+    pub selected: usize,
+    pub offsprings: usize,
+    /// The number of candidates that were mutated
+    pub mutations: usize,
+    /// The mean number of rules of a candidate
+    #[serde(default)]
+    pub average_rules: f64,
+    /// The mean number of constraints across all the rules of a candidate
+    #[serde(default)]
+    pub average_constraints: f64,
+    /// The number of candidates whose fitness was found in the cache
+    #[serde(default)]
+    pub cache_hits: usize,
+    /// The number of candidates that had to be evaluated
+    #[serde(default)]
+    pub cache_misses: usize,
+    /// The test accuracy of the best candidate
+    pub test_accuracy: Option<f64>,
+}
+
+/// The formats per generation statistics can be written in
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum StatsFormat {
+    Csv,
+    /// One JSON object per line
+    JsonLines,
+}
+
+#[derive(Error, Clone, PartialEq, Debug)]
+#[error("unknown stats format `{0}`, expected one of csv, jsonl")]
+pub struct StatsFormatParseError(String);
+
+impl std::str::FromStr for StatsFormat {
+    type Err = StatsFormatParseError;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        match input.to_ascii_lowercase().as_str() {
+            "csv" => Ok(StatsFormat::Csv),
+            "jsonl" | "ndjson" | "json" => Ok(StatsFormat::JsonLines),
+            _ => Err(StatsFormatParseError(input.to_owned())),
+        }
+    }
+}
+
+impl StatsFormat {
+    /// Guesses the format from the extension of a file. Defaults to `Csv`
+    pub fn from_path<T: AsRef<Path>>(path: T) -> StatsFormat {
+        path.as_ref()
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .and_then(|extension| extension.parse().ok())
+            .unwrap_or(StatsFormat::Csv)
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum StatsError {
+    #[error("failed to write stats: {0}")]
+    IoError(#[from] std::io::Error),
+
+    #[error("failed to serialize stats: {0}")]
+    SerializationError(#[from] serde_json::Error),
+}
+
+/// A record tagged with the run it belongs to, so that the records of repeated runs or folds can
+/// share a file
+#[derive(Serialize)]
+struct StatsRecord<'a> {
+    run: usize,
+    #[serde(flatten)]
+    stats: &'a GenerationStats,
+}
+
+const CSV_HEADER: &str = "run,generation,size,min_fitness,average_fitness,max_fitness,\
+median_fitness,fitness_stddev,diversity,selected,offsprings,mutations,average_rules,average_constraints,cache_hits,cache_misses,test_accuracy";
+
+/// Writes per generation statistics, one record per line
+#[derive(Debug)]
+pub struct StatsWriter<W: Write> {
+    writer: W,
+    format: StatsFormat,
+    wrote_header: bool,
+}
+
+impl<W: Write> StatsWriter<W> {
+    pub fn new(writer: W, format: StatsFormat) -> Self {
+        StatsWriter {
+            writer,
+            format,
+            wrote_header: false,
+        }
+    }
+
+    pub fn write(&mut self, run: usize, stats: &GenerationStats) -> Result<(), StatsError> {
+        match self.format {
+            StatsFormat::Csv => {
+                if !self.wrote_header {
+                    writeln!(self.writer, "{}", CSV_HEADER)?;
+                    self.wrote_header = true;
+                }
+                writeln!(
+                    self.writer,
+                    "{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{}",
+                    run,
+                    stats.generation,
+                    stats.size,
+                    stats.min_fitness,
+                    stats.average_fitness,
+                    stats.max_fitness,
+                    stats.median_fitness,
+                    stats.fitness_stddev,
+                    stats.diversity,
+                    stats.selected,
+                    stats.offsprings,
+                    stats.mutations,
+                    stats.average_rules,
+                    stats.average_constraints,
+                    stats.cache_hits,
+                    stats.cache_misses,
+                    stats
+                        .test_accuracy
+                        .map(|accuracy| accuracy.to_string())
+                        .unwrap_or_default(),
+                )?;
+            }
+            StatsFormat::JsonLines => {
+                serde_json::to_writer(&mut self.writer, &StatsRecord { run, stats })?;
+                writeln!(self.writer)?;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn flush(&mut self) -> Result<(), StatsError> {
+        self.writer.flush()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn stats() -> GenerationStats {
+        GenerationStats {
+            generation: 1,
+            size: 4,
+            min_fitness: 2.0,
+            average_fitness: 2.5,
+            max_fitness: 3.0,
+            median_fitness: 2.5,
+            fitness_stddev: 0.5,
+            diversity: 0.75,
+            selected: 2,
+            offsprings: 2,
+            mutations: 1,
+            average_rules: 2.5,
+            average_constraints: 7.0,
+            cache_hits: 3,
+            cache_misses: 1,
+            test_accuracy: None,
+        }
+    }
+
+    #[test]
+    fn test_csv() {
+        let mut writer = StatsWriter::new(vec![], StatsFormat::Csv);
+        writer.write(0, &stats()).unwrap();
+        writer.write(1, &stats()).unwrap();
+        let output = String::from_utf8(writer.writer).unwrap();
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0], CSV_HEADER);
+        assert_eq!(lines[2], "1,1,4,2,2.5,3,2.5,0.5,0.75,2,2,1,2.5,7,3,1,");
+    }
+
+    #[test]
+    fn test_json_lines() {
+        let mut writer = StatsWriter::new(vec![], StatsFormat::JsonLines);
+        writer.write(0, &stats()).unwrap();
+        let output = String::from_utf8(writer.writer).unwrap();
+        assert!(output.starts_with(r#"{"run":0,"generation":1,"size":4,"#));
+        assert!(output.ends_with("\"test_accuracy\":null}\n"));
+    }
+}